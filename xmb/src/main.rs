@@ -4,9 +4,9 @@ use std::{
     io::{BufWriter, Write},
     path::{Path, PathBuf},
 };
+use xmb_lib::meta::XmbMeta;
 use xmb_lib::xmb::Xmb;
-use xmb_lib::XmbFile;
-use xmltree::{Element, EmitterConfig};
+use xmb_lib::{compile_xml, parse_xml, FromXmlOptions, XmbFile, XmlOptions};
 
 /// Convert Smash XMB files to and from XML.
 #[derive(Parser)]
@@ -17,6 +17,19 @@ struct Cli {
     input: String,
     /// The output XML, XMB, or dot (graphviz) file
     output: Option<String>,
+    /// Preserve XML comments in a .meta file next to the output XMB
+    /// and restore them from the input's .meta file when converting to XML
+    #[arg(long)]
+    meta: bool,
+    /// Fail instead of warning for XML text or attributes that can't be stored in XMB
+    #[arg(long)]
+    strict: bool,
+    /// Warn about entries in the input XMB with unk1 values that won't be preserved when rebuilding
+    #[arg(long)]
+    verify: bool,
+    /// Recalculate counts, links, and lookup tables in the input XMB before converting
+    #[arg(long)]
+    repair: bool,
 }
 
 fn main() {
@@ -28,9 +41,23 @@ fn main() {
     match PathBuf::from(&input).extension().unwrap().to_str().unwrap() {
         "xml" => {
             let xml_text = std::io::Cursor::new(std::fs::read(input).unwrap());
-            let element = Element::parse(xml_text).unwrap();
-            let xmb_file = XmbFile::from_xml(&element);
-            let xmb = Xmb::from(&xmb_file);
+            let options = FromXmlOptions { strict: cli.strict };
+
+            // Comments are only available from the full XML tree.
+            // Otherwise, convert directly to XMB to save time and memory.
+            let (xmb, dropped, meta) = if cli.meta {
+                let element = parse_xml(xml_text).unwrap();
+                let (xmb_file, dropped) =
+                    XmbFile::from_xml_with_options(&element, &options).unwrap();
+                let meta = XmbMeta::from_xml(&element);
+                (Xmb::from_owned(xmb_file), dropped, Some(meta))
+            } else {
+                let (xmb, dropped) = compile_xml(xml_text, &options).unwrap();
+                (xmb, dropped, None)
+            };
+            for d in dropped {
+                eprintln!("Warning: Ignoring {d}");
+            }
 
             // Replace the xml extension.
             // Ex: model.xmb.xml -> model.xmb.xmb.
@@ -40,13 +67,36 @@ fn main() {
                 .unwrap_or_else(|| PathBuf::from(input).with_extension("xmb"));
 
             match output.extension().unwrap().to_str().unwrap() {
-                "xmb" => xmb.write_to_file(output).unwrap(),
+                "xmb" => {
+                    if let Some(meta) = meta {
+                        // Ex: model.xmb -> model.xmb.meta.
+                        meta.write_to_file(append_extension(&output, "meta"))
+                            .unwrap();
+                    }
+                    xmb.write_to_file(output).unwrap()
+                }
                 "dot" => write_dot_graph(output, &xmb).unwrap(),
                 _ => panic!("Unsupported output extension for XML input"),
             }
         }
         "xmb" => {
-            let xmb = Xmb::from_file(input).unwrap();
+            // The counts in the header may be wrong, so only use the offsets when repairing.
+            let mut xmb = if cli.repair {
+                let mut reader = std::io::Cursor::new(std::fs::read(input).unwrap());
+                Xmb::read_lenient(&mut reader).unwrap()
+            } else {
+                Xmb::from_file(input).unwrap()
+            };
+            if cli.repair {
+                for repair in xmb.repair() {
+                    eprintln!("Repaired: {repair}");
+                }
+            }
+            if cli.verify {
+                for mismatch in xmb.verify_unk1() {
+                    eprintln!("Warning: {mismatch}");
+                }
+            }
 
             // Append .xml to the existing file to avoid overwriting existing files.
             // Ex: model.xmb -> model.xmb.xml.
@@ -58,20 +108,21 @@ fn main() {
             match output.extension().unwrap().to_str().unwrap() {
                 "xmb" => xmb.write_to_file(output).unwrap(),
                 "xml" => {
-                    let xmb_file = XmbFile::try_from(&xmb).unwrap();
-
-                    let element = xmb_file.to_xml().unwrap();
-
-                    // Match the output of the original Python script where possible.
-                    let config = EmitterConfig::new()
-                        .perform_indent(true)
-                        .indent_string("    ")
-                        .pad_self_closing(false);
-
-                    // Write the xml.
                     let mut writer =
                         std::io::BufWriter::new(std::fs::File::create(output).unwrap());
-                    element.write_with_config(&mut writer, config).unwrap();
+                    let options = XmlOptions::ssbu_tools();
+
+                    let meta_path = append_extension(Path::new(input), "meta");
+                    if cli.meta && meta_path.exists() {
+                        let xmb_file = XmbFile::try_from(&xmb).unwrap();
+                        let meta = XmbMeta::from_file(meta_path).unwrap();
+                        xmb_file
+                            .write_xml_with_meta(&mut writer, &meta, &options)
+                            .unwrap();
+                    } else {
+                        // Write directly without creating an XML tree to save time and memory.
+                        xmb.write_xml(&mut writer, &options).unwrap();
+                    }
                 }
                 "dot" => write_dot_graph(output, &xmb).unwrap(),
                 _ => panic!("Unsupported output extension for XMB input"),
@@ -81,6 +132,13 @@ fn main() {
     }
 }
 
+fn append_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".");
+    path.push(extension);
+    PathBuf::from(path)
+}
+
 fn write_dot_graph<P: AsRef<Path>>(output: P, xmb: &Xmb) -> std::io::Result<()> {
     let mut file = BufWriter::new(std::fs::File::create(output)?);
 
@@ -0,0 +1,19 @@
+fn main() {
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    let config = cbindgen::Config::from_file(format!("{crate_dir}/cbindgen.toml")).unwrap();
+
+    // Only write to OUT_DIR to support read-only source directories.
+    // The tests check that the header in include is up to date.
+    cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("failed to generate C bindings")
+        .write_to_file(format!("{out_dir}/xmb_capi.h"));
+
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    // cbindgen also parses types exported from xmb_lib.
+    println!("cargo:rerun-if-changed=../xmb_lib/src");
+}
@@ -0,0 +1,290 @@
+#ifndef XMB_CAPI_H
+#define XMB_CAPI_H
+
+/* This file is generated by cbindgen. Do not edit it manually. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The result of a function that can fail.
+ */
+typedef enum XmbStatus {
+  XMB_STATUS_OK = 0,
+  /**
+   * A required pointer argument was null.
+   */
+  XMB_STATUS_NULL_POINTER,
+  /**
+   * A string argument was not valid UTF-8.
+   */
+  XMB_STATUS_INVALID_UTF8,
+  /**
+   * An index was greater than or equal to the number of items.
+   */
+  XMB_STATUS_INDEX_OUT_OF_BOUNDS,
+  /**
+   * No attribute exists with the given name.
+   */
+  XMB_STATUS_NOT_FOUND,
+  /**
+   * Reading or writing a file failed.
+   */
+  XMB_STATUS_IO,
+  /**
+   * The data is not a valid XMB file.
+   */
+  XMB_STATUS_READ,
+  /**
+   * Writing the data failed.
+   */
+  XMB_STATUS_WRITE,
+  /**
+   * Converting between [Xmb] and [XmbFile] failed.
+   */
+  XMB_STATUS_CONVERT,
+  /**
+   * An unexpected error occurred in the library.
+   */
+  XMB_STATUS_PANIC,
+} XmbStatus;
+
+/**
+ * A flattened tree of named nodes with each node containing a collection of named attributes.
+ * This corresponds to an XML document.
+ */
+typedef struct Xmb Xmb;
+
+typedef struct XmbFile XmbFile;
+
+typedef struct XmbFileEntry XmbFileEntry;
+
+/**
+ * Bytes owned by the caller that must be freed with [xmb_buffer_free].
+ */
+typedef struct XmbBuffer {
+  uint8_t *data;
+  size_t len;
+} XmbBuffer;
+
+/**
+ * A UTF-8 string borrowed from a handle that is not null terminated.
+ */
+typedef struct XmbStr {
+  const uint8_t *data;
+  size_t len;
+} XmbStr;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * The message for the most recent error on the current thread or an empty string.
+ * The pointer is valid until the next function call that fails on the current thread.
+ */
+const char *xmb_last_error_message(void);
+
+/**
+ * Free bytes returned by functions like [xmb_write_buffer].
+ * # Safety
+ * `buffer` must be returned from this library and not already freed.
+ */
+void xmb_buffer_free(struct XmbBuffer buffer);
+
+/**
+ * Read an [Xmb] from `len` bytes at `data` and store the handle in `out`.
+ * # Safety
+ * `data` must point to `len` readable bytes, and `out` must be valid for writes.
+ */
+enum XmbStatus xmb_read_buffer(const uint8_t *data, size_t len, struct Xmb **out);
+
+/**
+ * Read an [Xmb] from the file at `path` and store the handle in `out`.
+ * # Safety
+ * `path` must be a null terminated string, and `out` must be valid for writes.
+ */
+enum XmbStatus xmb_read_file(const char *path, struct Xmb **out);
+
+/**
+ * Write the XMB data for `xmb` to a new buffer stored in `out`.
+ * # Safety
+ * `xmb` must be a valid handle, and `out` must be valid for writes.
+ */
+enum XmbStatus xmb_write_buffer(const struct Xmb *xmb, struct XmbBuffer *out);
+
+/**
+ * Free a handle returned by functions like [xmb_read_buffer].
+ * # Safety
+ * `xmb` must be null or a handle returned from this library that is not already freed.
+ */
+void xmb_free(struct Xmb *xmb);
+
+/**
+ * Convert `xmb` to a new [XmbFile] handle stored in `out`.
+ * # Safety
+ * `xmb` must be a valid handle, and `out` must be valid for writes.
+ */
+enum XmbStatus xmb_to_xmb_file(const struct Xmb *xmb, struct XmbFile **out);
+
+/**
+ * Convert `xmb_file` to a new [Xmb] handle stored in `out`.
+ * # Safety
+ * `xmb_file` must be a valid handle, and `out` must be valid for writes.
+ */
+enum XmbStatus xmb_file_to_xmb(const struct XmbFile *xmb_file, struct Xmb **out);
+
+/**
+ * Create an [XmbFile] with no entries.
+ * The handle must be freed with [xmb_file_free].
+ */
+struct XmbFile *xmb_file_new(void);
+
+/**
+ * Read an [XmbFile] from `len` bytes at `data` and store the handle in `out`.
+ * # Safety
+ * `data` must point to `len` readable bytes, and `out` must be valid for writes.
+ */
+enum XmbStatus xmb_file_read_buffer(const uint8_t *data, size_t len, struct XmbFile **out);
+
+/**
+ * Read an [XmbFile] from the file at `path` and store the handle in `out`.
+ * # Safety
+ * `path` must be a null terminated string, and `out` must be valid for writes.
+ */
+enum XmbStatus xmb_file_read_file(const char *path, struct XmbFile **out);
+
+/**
+ * Write the XMB data for `xmb_file` to a new buffer stored in `out`.
+ * # Safety
+ * `xmb_file` must be a valid handle, and `out` must be valid for writes.
+ */
+enum XmbStatus xmb_file_write_buffer(const struct XmbFile *xmb_file, struct XmbBuffer *out);
+
+/**
+ * Free a handle returned by functions like [xmb_file_new].
+ * # Safety
+ * `xmb_file` must be null or a handle returned from this library that is not already freed.
+ */
+void xmb_file_free(struct XmbFile *xmb_file);
+
+/**
+ * The number of root entries in `xmb_file` or 0 if `xmb_file` is null.
+ * # Safety
+ * `xmb_file` must be null or a valid handle.
+ */
+size_t xmb_file_entry_count(const struct XmbFile *xmb_file);
+
+/**
+ * The root entry at `index` or null if the index is out of bounds.
+ * # Safety
+ * `xmb_file` must be null or a valid handle.
+ */
+struct XmbFileEntry *xmb_file_entry(struct XmbFile *xmb_file, size_t index);
+
+/**
+ * Add a root entry named `name` and return the new entry or null on failure.
+ * # Safety
+ * `xmb_file` must be a valid handle, and `name` must be a null terminated string.
+ */
+struct XmbFileEntry *xmb_file_add_entry(struct XmbFile *xmb_file, const char *name);
+
+/**
+ * Remove the root entry at `index`.
+ * # Safety
+ * `xmb_file` must be a valid handle.
+ */
+enum XmbStatus xmb_file_remove_entry(struct XmbFile *xmb_file, size_t index);
+
+/**
+ * The name of `entry` or an empty string if `entry` is null.
+ * # Safety
+ * `entry` must be null or a valid entry.
+ */
+struct XmbStr xmb_entry_name(const struct XmbFileEntry *entry);
+
+/**
+ * Rename `entry` to `name`.
+ * # Safety
+ * `entry` must be a valid entry, and `name` must be a null terminated string.
+ */
+enum XmbStatus xmb_entry_set_name(struct XmbFileEntry *entry, const char *name);
+
+/**
+ * The number of attributes for `entry` or 0 if `entry` is null.
+ * # Safety
+ * `entry` must be null or a valid entry.
+ */
+size_t xmb_entry_attribute_count(const struct XmbFileEntry *entry);
+
+/**
+ * Store the name and value of the attribute at `index` in `name` and `value`.
+ * # Safety
+ * `entry` must be a valid entry, and `name` and `value` must be valid for writes.
+ */
+enum XmbStatus xmb_entry_attribute(const struct XmbFileEntry *entry,
+                                   size_t index,
+                                   struct XmbStr *name,
+                                   struct XmbStr *value);
+
+/**
+ * Store the value of the attribute with the given `name` in `value`.
+ * # Safety
+ * `entry` must be a valid entry, `name` must be a null terminated string, and `value` must be valid for writes.
+ */
+enum XmbStatus xmb_entry_get_attribute(const struct XmbFileEntry *entry,
+                                       const char *name,
+                                       struct XmbStr *value);
+
+/**
+ * Set the attribute `name` to `value` and add the attribute if it doesn't exist.
+ * # Safety
+ * `entry` must be a valid entry, and `name` and `value` must be null terminated strings.
+ */
+enum XmbStatus xmb_entry_set_attribute(struct XmbFileEntry *entry,
+                                       const char *name,
+                                       const char *value);
+
+/**
+ * Remove the attribute `name` while preserving the order of the remaining attributes.
+ * # Safety
+ * `entry` must be a valid entry, and `name` must be a null terminated string.
+ */
+enum XmbStatus xmb_entry_remove_attribute(struct XmbFileEntry *entry, const char *name);
+
+/**
+ * The number of children for `entry` or 0 if `entry` is null.
+ * # Safety
+ * `entry` must be null or a valid entry.
+ */
+size_t xmb_entry_child_count(const struct XmbFileEntry *entry);
+
+/**
+ * The child at `index` or null if the index is out of bounds.
+ * # Safety
+ * `entry` must be null or a valid entry.
+ */
+struct XmbFileEntry *xmb_entry_child(struct XmbFileEntry *entry, size_t index);
+
+/**
+ * Add a child named `name` as the last child and return the new entry or null on failure.
+ * # Safety
+ * `entry` must be a valid entry, and `name` must be a null terminated string.
+ */
+struct XmbFileEntry *xmb_entry_add_child(struct XmbFileEntry *entry, const char *name);
+
+/**
+ * Remove the child at `index`.
+ * # Safety
+ * `entry` must be a valid entry.
+ */
+enum XmbStatus xmb_entry_remove_child(struct XmbFileEntry *entry, size_t index);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* XMB_CAPI_H */
@@ -0,0 +1,765 @@
+//! A C API for [xmb_lib] for use from other languages like C, C++, and C#.
+//!
+//! The generated header is `include/xmb_capi.h`.
+//! [Xmb], [XmbFile], and [XmbFileEntry] are opaque handles.
+//! Functions that can fail return an [XmbStatus] with details available from [xmb_last_error_message].
+//! Panics are caught and reported as [XmbStatus::Panic] or a null or empty result instead of unwinding into the caller.
+//!
+//! Input strings are null terminated UTF-8.
+//! Output strings are [XmbStr] values borrowed from the handle that aren't null terminated.
+//! Entry pointers and borrowed strings are invalidated by any changes to the containing [XmbFile].
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::fmt::Display;
+use std::panic::AssertUnwindSafe;
+use xmb_lib::xmb::Xmb;
+use xmb_lib::{XmbFile, XmbFileEntry};
+
+/// The result of a function that can fail.
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum XmbStatus {
+    Ok = 0,
+    /// A required pointer argument was null.
+    NullPointer,
+    /// A string argument was not valid UTF-8.
+    InvalidUtf8,
+    /// An index was greater than or equal to the number of items.
+    IndexOutOfBounds,
+    /// No attribute exists with the given name.
+    NotFound,
+    /// Reading or writing a file failed.
+    Io,
+    /// The data is not a valid XMB file.
+    Read,
+    /// Writing the data failed.
+    Write,
+    /// Converting between [Xmb] and [XmbFile] failed.
+    Convert,
+    /// An unexpected error occurred in the library.
+    Panic,
+}
+
+/// A UTF-8 string borrowed from a handle that is not null terminated.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct XmbStr {
+    pub data: *const u8,
+    pub len: usize,
+}
+
+/// Bytes owned by the caller that must be freed with [xmb_buffer_free].
+#[repr(C)]
+#[derive(Debug)]
+pub struct XmbBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+/// The message for the most recent error on the current thread or an empty string.
+/// The pointer is valid until the next function call that fails on the current thread.
+#[no_mangle]
+pub extern "C" fn xmb_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ptr())
+}
+
+/// Free bytes returned by functions like [xmb_write_buffer].
+/// # Safety
+/// `buffer` must be returned from this library and not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_buffer_free(buffer: XmbBuffer) {
+    catch_panic((), || {
+        if !buffer.data.is_null() {
+            drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+                buffer.data,
+                buffer.len,
+            )));
+        }
+    })
+}
+
+/// Read an [Xmb] from `len` bytes at `data` and store the handle in `out`.
+/// # Safety
+/// `data` must point to `len` readable bytes, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_read_buffer(
+    data: *const u8,
+    len: usize,
+    out: *mut *mut Xmb,
+) -> XmbStatus {
+    catch_panic(XmbStatus::Panic, || {
+        if data.is_null() || out.is_null() {
+            return null_pointer();
+        }
+        let bytes = std::slice::from_raw_parts(data, len);
+        match Xmb::read(&mut std::io::Cursor::new(bytes)) {
+            Ok(xmb) => {
+                *out = Box::into_raw(Box::new(xmb));
+                XmbStatus::Ok
+            }
+            Err(e) => error(XmbStatus::Read, e),
+        }
+    })
+}
+
+/// Read an [Xmb] from the file at `path` and store the handle in `out`.
+/// # Safety
+/// `path` must be a null terminated string, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_read_file(path: *const c_char, out: *mut *mut Xmb) -> XmbStatus {
+    catch_panic(XmbStatus::Panic, || {
+        if out.is_null() {
+            return null_pointer();
+        }
+        let path = match input_str(path) {
+            Ok(path) => path,
+            Err(status) => return status,
+        };
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) => return error(XmbStatus::Io, e),
+        };
+        xmb_read_buffer(bytes.as_ptr(), bytes.len(), out)
+    })
+}
+
+/// Write the XMB data for `xmb` to a new buffer stored in `out`.
+/// # Safety
+/// `xmb` must be a valid handle, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_write_buffer(xmb: *const Xmb, out: *mut XmbBuffer) -> XmbStatus {
+    catch_panic(XmbStatus::Panic, || {
+        if xmb.is_null() || out.is_null() {
+            return null_pointer();
+        }
+        let mut writer = std::io::Cursor::new(Vec::new());
+        match (*xmb).write(&mut writer) {
+            Ok(()) => {
+                *out = buffer(writer.into_inner());
+                XmbStatus::Ok
+            }
+            Err(e) => error(XmbStatus::Write, e),
+        }
+    })
+}
+
+/// Free a handle returned by functions like [xmb_read_buffer].
+/// # Safety
+/// `xmb` must be null or a handle returned from this library that is not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_free(xmb: *mut Xmb) {
+    catch_panic((), || {
+        if !xmb.is_null() {
+            drop(Box::from_raw(xmb));
+        }
+    })
+}
+
+/// Convert `xmb` to a new [XmbFile] handle stored in `out`.
+/// # Safety
+/// `xmb` must be a valid handle, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_to_xmb_file(xmb: *const Xmb, out: *mut *mut XmbFile) -> XmbStatus {
+    catch_panic(XmbStatus::Panic, || {
+        if xmb.is_null() || out.is_null() {
+            return null_pointer();
+        }
+        match XmbFile::try_from(&*xmb) {
+            Ok(xmb_file) => {
+                *out = Box::into_raw(Box::new(xmb_file));
+                XmbStatus::Ok
+            }
+            Err(e) => error(XmbStatus::Convert, e),
+        }
+    })
+}
+
+/// Convert `xmb_file` to a new [Xmb] handle stored in `out`.
+/// # Safety
+/// `xmb_file` must be a valid handle, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_file_to_xmb(
+    xmb_file: *const XmbFile,
+    out: *mut *mut Xmb,
+) -> XmbStatus {
+    catch_panic(XmbStatus::Panic, || {
+        if xmb_file.is_null() || out.is_null() {
+            return null_pointer();
+        }
+        *out = Box::into_raw(Box::new(Xmb::from(&*xmb_file)));
+        XmbStatus::Ok
+    })
+}
+
+/// Create an [XmbFile] with no entries.
+/// The handle must be freed with [xmb_file_free].
+#[no_mangle]
+pub extern "C" fn xmb_file_new() -> *mut XmbFile {
+    catch_panic(std::ptr::null_mut(), || {
+        Box::into_raw(Box::new(XmbFile {
+            entries: Vec::new(),
+        }))
+    })
+}
+
+/// Read an [XmbFile] from `len` bytes at `data` and store the handle in `out`.
+/// # Safety
+/// `data` must point to `len` readable bytes, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_file_read_buffer(
+    data: *const u8,
+    len: usize,
+    out: *mut *mut XmbFile,
+) -> XmbStatus {
+    catch_panic(XmbStatus::Panic, || {
+        let mut xmb = std::ptr::null_mut();
+        let status = xmb_read_buffer(data, len, &mut xmb);
+        if status != XmbStatus::Ok {
+            return status;
+        }
+        let status = xmb_to_xmb_file(xmb, out);
+        xmb_free(xmb);
+        status
+    })
+}
+
+/// Read an [XmbFile] from the file at `path` and store the handle in `out`.
+/// # Safety
+/// `path` must be a null terminated string, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_file_read_file(
+    path: *const c_char,
+    out: *mut *mut XmbFile,
+) -> XmbStatus {
+    catch_panic(XmbStatus::Panic, || {
+        let mut xmb = std::ptr::null_mut();
+        let status = xmb_read_file(path, &mut xmb);
+        if status != XmbStatus::Ok {
+            return status;
+        }
+        let status = xmb_to_xmb_file(xmb, out);
+        xmb_free(xmb);
+        status
+    })
+}
+
+/// Write the XMB data for `xmb_file` to a new buffer stored in `out`.
+/// # Safety
+/// `xmb_file` must be a valid handle, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_file_write_buffer(
+    xmb_file: *const XmbFile,
+    out: *mut XmbBuffer,
+) -> XmbStatus {
+    catch_panic(XmbStatus::Panic, || {
+        if xmb_file.is_null() {
+            return null_pointer();
+        }
+        let xmb = Xmb::from(&*xmb_file);
+        xmb_write_buffer(&xmb, out)
+    })
+}
+
+/// Free a handle returned by functions like [xmb_file_new].
+/// # Safety
+/// `xmb_file` must be null or a handle returned from this library that is not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_file_free(xmb_file: *mut XmbFile) {
+    catch_panic((), || {
+        if !xmb_file.is_null() {
+            drop(Box::from_raw(xmb_file));
+        }
+    })
+}
+
+/// The number of root entries in `xmb_file` or 0 if `xmb_file` is null.
+/// # Safety
+/// `xmb_file` must be null or a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_file_entry_count(xmb_file: *const XmbFile) -> usize {
+    catch_panic(0, || {
+        xmb_file
+            .as_ref()
+            .map(|f| f.entries.len())
+            .unwrap_or_default()
+    })
+}
+
+/// The root entry at `index` or null if the index is out of bounds.
+/// # Safety
+/// `xmb_file` must be null or a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_file_entry(xmb_file: *mut XmbFile, index: usize) -> *mut XmbFileEntry {
+    catch_panic(std::ptr::null_mut(), || {
+        xmb_file
+            .as_mut()
+            .and_then(|f| f.entries.get_mut(index))
+            .map_or(std::ptr::null_mut(), |e| e as *mut _)
+    })
+}
+
+/// Add a root entry named `name` and return the new entry or null on failure.
+/// # Safety
+/// `xmb_file` must be a valid handle, and `name` must be a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_file_add_entry(
+    xmb_file: *mut XmbFile,
+    name: *const c_char,
+) -> *mut XmbFileEntry {
+    catch_panic(std::ptr::null_mut(), || {
+        let Some(xmb_file) = xmb_file.as_mut() else {
+            null_pointer();
+            return std::ptr::null_mut();
+        };
+        match input_str(name) {
+            Ok(name) => {
+                xmb_file.entries.push(XmbFileEntry::new(name));
+                xmb_file.entries.last_mut().unwrap()
+            }
+            Err(_) => std::ptr::null_mut(),
+        }
+    })
+}
+
+/// Remove the root entry at `index`.
+/// # Safety
+/// `xmb_file` must be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_file_remove_entry(xmb_file: *mut XmbFile, index: usize) -> XmbStatus {
+    catch_panic(XmbStatus::Panic, || match xmb_file.as_mut() {
+        Some(xmb_file) => remove_index(&mut xmb_file.entries, index),
+        None => null_pointer(),
+    })
+}
+
+/// The name of `entry` or an empty string if `entry` is null.
+/// # Safety
+/// `entry` must be null or a valid entry.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_entry_name(entry: *const XmbFileEntry) -> XmbStr {
+    catch_panic(output_str(""), || {
+        output_str(entry.as_ref().map(|e| e.name.as_str()).unwrap_or_default())
+    })
+}
+
+/// Rename `entry` to `name`.
+/// # Safety
+/// `entry` must be a valid entry, and `name` must be a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_entry_set_name(
+    entry: *mut XmbFileEntry,
+    name: *const c_char,
+) -> XmbStatus {
+    catch_panic(XmbStatus::Panic, || {
+        let Some(entry) = entry.as_mut() else {
+            return null_pointer();
+        };
+        match input_str(name) {
+            Ok(name) => {
+                entry.rename(name);
+                XmbStatus::Ok
+            }
+            Err(status) => status,
+        }
+    })
+}
+
+/// The number of attributes for `entry` or 0 if `entry` is null.
+/// # Safety
+/// `entry` must be null or a valid entry.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_entry_attribute_count(entry: *const XmbFileEntry) -> usize {
+    catch_panic(0, || {
+        entry
+            .as_ref()
+            .map(|e| e.attributes.len())
+            .unwrap_or_default()
+    })
+}
+
+/// Store the name and value of the attribute at `index` in `name` and `value`.
+/// # Safety
+/// `entry` must be a valid entry, and `name` and `value` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_entry_attribute(
+    entry: *const XmbFileEntry,
+    index: usize,
+    name: *mut XmbStr,
+    value: *mut XmbStr,
+) -> XmbStatus {
+    catch_panic(XmbStatus::Panic, || {
+        let Some(entry) = entry.as_ref() else {
+            return null_pointer();
+        };
+        if name.is_null() || value.is_null() {
+            return null_pointer();
+        }
+        match entry.attributes.get_index(index) {
+            Some((k, v)) => {
+                *name = output_str(k);
+                *value = output_str(v);
+                XmbStatus::Ok
+            }
+            None => index_out_of_bounds(index, entry.attributes.len()),
+        }
+    })
+}
+
+/// Store the value of the attribute with the given `name` in `value`.
+/// # Safety
+/// `entry` must be a valid entry, `name` must be a null terminated string, and `value` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_entry_get_attribute(
+    entry: *const XmbFileEntry,
+    name: *const c_char,
+    value: *mut XmbStr,
+) -> XmbStatus {
+    catch_panic(XmbStatus::Panic, || {
+        let Some(entry) = entry.as_ref() else {
+            return null_pointer();
+        };
+        if value.is_null() {
+            return null_pointer();
+        }
+        let name = match input_str(name) {
+            Ok(name) => name,
+            Err(status) => return status,
+        };
+        match entry.get_attr(name) {
+            Some(v) => {
+                *value = output_str(v);
+                XmbStatus::Ok
+            }
+            None => not_found(name),
+        }
+    })
+}
+
+/// Set the attribute `name` to `value` and add the attribute if it doesn't exist.
+/// # Safety
+/// `entry` must be a valid entry, and `name` and `value` must be null terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_entry_set_attribute(
+    entry: *mut XmbFileEntry,
+    name: *const c_char,
+    value: *const c_char,
+) -> XmbStatus {
+    catch_panic(XmbStatus::Panic, || {
+        let Some(entry) = entry.as_mut() else {
+            return null_pointer();
+        };
+        match (input_str(name), input_str(value)) {
+            (Ok(name), Ok(value)) => {
+                entry.set_attr(name, value);
+                XmbStatus::Ok
+            }
+            (Err(status), _) | (_, Err(status)) => status,
+        }
+    })
+}
+
+/// Remove the attribute `name` while preserving the order of the remaining attributes.
+/// # Safety
+/// `entry` must be a valid entry, and `name` must be a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_entry_remove_attribute(
+    entry: *mut XmbFileEntry,
+    name: *const c_char,
+) -> XmbStatus {
+    catch_panic(XmbStatus::Panic, || {
+        let Some(entry) = entry.as_mut() else {
+            return null_pointer();
+        };
+        let name = match input_str(name) {
+            Ok(name) => name,
+            Err(status) => return status,
+        };
+        match entry.remove_attr(name) {
+            Some(_) => XmbStatus::Ok,
+            None => not_found(name),
+        }
+    })
+}
+
+/// The number of children for `entry` or 0 if `entry` is null.
+/// # Safety
+/// `entry` must be null or a valid entry.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_entry_child_count(entry: *const XmbFileEntry) -> usize {
+    catch_panic(0, || {
+        entry.as_ref().map(|e| e.children.len()).unwrap_or_default()
+    })
+}
+
+/// The child at `index` or null if the index is out of bounds.
+/// # Safety
+/// `entry` must be null or a valid entry.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_entry_child(
+    entry: *mut XmbFileEntry,
+    index: usize,
+) -> *mut XmbFileEntry {
+    catch_panic(std::ptr::null_mut(), || {
+        entry
+            .as_mut()
+            .and_then(|e| e.children.get_mut(index))
+            .map_or(std::ptr::null_mut(), |c| c as *mut _)
+    })
+}
+
+/// Add a child named `name` as the last child and return the new entry or null on failure.
+/// # Safety
+/// `entry` must be a valid entry, and `name` must be a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_entry_add_child(
+    entry: *mut XmbFileEntry,
+    name: *const c_char,
+) -> *mut XmbFileEntry {
+    catch_panic(std::ptr::null_mut(), || {
+        let Some(entry) = entry.as_mut() else {
+            null_pointer();
+            return std::ptr::null_mut();
+        };
+        match input_str(name) {
+            Ok(name) => {
+                entry.push_child(XmbFileEntry::new(name));
+                entry.children.last_mut().unwrap()
+            }
+            Err(_) => std::ptr::null_mut(),
+        }
+    })
+}
+
+/// Remove the child at `index`.
+/// # Safety
+/// `entry` must be a valid entry.
+#[no_mangle]
+pub unsafe extern "C" fn xmb_entry_remove_child(
+    entry: *mut XmbFileEntry,
+    index: usize,
+) -> XmbStatus {
+    catch_panic(XmbStatus::Panic, || match entry.as_mut() {
+        Some(entry) => remove_index(&mut entry.children, index),
+        None => null_pointer(),
+    })
+}
+
+// Unwinding across the FFI boundary is undefined behavior, so convert panics to errors.
+fn catch_panic<T, F: FnOnce() -> T>(on_panic: T, f: F) -> T {
+    std::panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown error");
+        error(XmbStatus::Panic, format!("panicked: {message}"));
+        on_panic
+    })
+}
+
+fn error<E: Display>(status: XmbStatus, e: E) -> XmbStatus {
+    // Null bytes would truncate the message in C.
+    let message = CString::new(e.to_string().replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|l| *l.borrow_mut() = message);
+    status
+}
+
+fn null_pointer() -> XmbStatus {
+    error(XmbStatus::NullPointer, "a required argument was null")
+}
+
+fn not_found(name: &str) -> XmbStatus {
+    error(XmbStatus::NotFound, format!("no attribute named {name:?}"))
+}
+
+fn index_out_of_bounds(index: usize, len: usize) -> XmbStatus {
+    error(
+        XmbStatus::IndexOutOfBounds,
+        format!("index {index} is out of bounds for length {len}"),
+    )
+}
+
+fn remove_index(entries: &mut Vec<XmbFileEntry>, index: usize) -> XmbStatus {
+    if index < entries.len() {
+        entries.remove(index);
+        XmbStatus::Ok
+    } else {
+        index_out_of_bounds(index, entries.len())
+    }
+}
+
+unsafe fn input_str<'a>(s: *const c_char) -> Result<&'a str, XmbStatus> {
+    if s.is_null() {
+        return Err(null_pointer());
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|e| error(XmbStatus::InvalidUtf8, e))
+}
+
+fn output_str(s: &str) -> XmbStr {
+    XmbStr {
+        data: s.as_ptr(),
+        len: s.len(),
+    }
+}
+
+fn buffer(bytes: Vec<u8>) -> XmbBuffer {
+    let len = bytes.len();
+    let data = Box::into_raw(bytes.into_boxed_slice()) as *mut u8;
+    XmbBuffer { data, len }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_str<'a>(s: XmbStr) -> &'a str {
+        unsafe { std::str::from_utf8(std::slice::from_raw_parts(s.data, s.len)).unwrap() }
+    }
+
+    #[test]
+    fn edit_write_read() {
+        unsafe {
+            let xmb_file = xmb_file_new();
+            let root = xmb_file_add_entry(xmb_file, c"model".as_ptr());
+            assert_eq!(
+                XmbStatus::Ok,
+                xmb_entry_set_attribute(root, c"type".as_ptr(), c"effect_main".as_ptr())
+            );
+            let child = xmb_entry_add_child(root, c"shadow".as_ptr());
+            xmb_entry_set_attribute(child, c"caster".as_ptr(), c"0".as_ptr());
+            xmb_entry_set_attribute(child, c"id".as_ptr(), c"a".as_ptr());
+            assert_eq!(
+                XmbStatus::Ok,
+                xmb_entry_remove_attribute(child, c"id".as_ptr())
+            );
+
+            let mut buffer = XmbBuffer {
+                data: std::ptr::null_mut(),
+                len: 0,
+            };
+            assert_eq!(XmbStatus::Ok, xmb_file_write_buffer(xmb_file, &mut buffer));
+            xmb_file_free(xmb_file);
+
+            let mut xmb_file = std::ptr::null_mut();
+            assert_eq!(
+                XmbStatus::Ok,
+                xmb_file_read_buffer(buffer.data, buffer.len, &mut xmb_file)
+            );
+            xmb_buffer_free(buffer);
+
+            assert_eq!(1, xmb_file_entry_count(xmb_file));
+            let root = xmb_file_entry(xmb_file, 0);
+            assert_eq!("model", to_str(xmb_entry_name(root)));
+            let mut value = output_str("");
+            assert_eq!(
+                XmbStatus::Ok,
+                xmb_entry_get_attribute(root, c"type".as_ptr(), &mut value)
+            );
+            assert_eq!("effect_main", to_str(value));
+
+            assert_eq!(1, xmb_entry_child_count(root));
+            let child = xmb_entry_child(root, 0);
+            assert_eq!("shadow", to_str(xmb_entry_name(child)));
+            assert_eq!(1, xmb_entry_attribute_count(child));
+            let mut name = output_str("");
+            assert_eq!(
+                XmbStatus::Ok,
+                xmb_entry_attribute(child, 0, &mut name, &mut value)
+            );
+            assert_eq!(("caster", "0"), (to_str(name), to_str(value)));
+
+            xmb_file_free(xmb_file);
+        }
+    }
+
+    #[test]
+    fn header_up_to_date() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/xmb_capi.h"));
+        let checked_in = include_str!("../include/xmb_capi.h");
+        assert!(
+            generated == checked_in,
+            "include/xmb_capi.h is out of date, copy {}/xmb_capi.h to update it",
+            env!("OUT_DIR")
+        );
+    }
+
+    #[test]
+    fn panic_status() {
+        assert_eq!(
+            XmbStatus::Panic,
+            catch_panic(XmbStatus::Panic, || -> XmbStatus { panic!("oops") })
+        );
+        assert_eq!(
+            "panicked: oops",
+            unsafe { CStr::from_ptr(xmb_last_error_message()) }
+                .to_str()
+                .unwrap()
+        );
+
+        let message = String::from("index");
+        assert!(catch_panic(std::ptr::null_mut::<XmbFile>(), || panic!("{message}")).is_null());
+        assert_eq!(
+            "panicked: index",
+            unsafe { CStr::from_ptr(xmb_last_error_message()) }
+                .to_str()
+                .unwrap()
+        );
+
+        assert_eq!(
+            XmbStatus::Ok,
+            catch_panic(XmbStatus::Panic, || XmbStatus::Ok)
+        );
+    }
+
+    #[test]
+    fn errors() {
+        unsafe {
+            let mut xmb = std::ptr::null_mut();
+            let data = b"XMB ";
+            assert_eq!(
+                XmbStatus::Read,
+                xmb_read_buffer(data.as_ptr(), data.len(), &mut xmb)
+            );
+            assert!(xmb.is_null());
+            assert!(!CStr::from_ptr(xmb_last_error_message()).is_empty());
+
+            assert_eq!(
+                XmbStatus::NullPointer,
+                xmb_read_buffer(std::ptr::null(), 0, &mut xmb)
+            );
+
+            let xmb_file = xmb_file_new();
+            assert!(xmb_file_entry(xmb_file, 0).is_null());
+            assert_eq!(
+                XmbStatus::IndexOutOfBounds,
+                xmb_file_remove_entry(xmb_file, 0)
+            );
+            assert_eq!(
+                "index 0 is out of bounds for length 0",
+                CStr::from_ptr(xmb_last_error_message()).to_str().unwrap()
+            );
+
+            let root = xmb_file_add_entry(xmb_file, c"root".as_ptr());
+            let mut value = output_str("");
+            assert_eq!(
+                XmbStatus::NotFound,
+                xmb_entry_get_attribute(root, c"id".as_ptr(), &mut value)
+            );
+
+            let invalid = [0xFFu8, 0];
+            assert!(xmb_entry_add_child(root, invalid.as_ptr() as *const c_char).is_null());
+            assert_eq!(
+                XmbStatus::InvalidUtf8,
+                xmb_entry_set_name(root, invalid.as_ptr() as *const c_char)
+            );
+
+            xmb_file_free(xmb_file);
+        }
+    }
+}
@@ -5,7 +5,7 @@ fuzz_target!(|xmb: xmb_lib::XmbFile| {
     // Test that the XmbFile <-> XML conversion is 1:1.
     // This conversion shouldn't lose any information.
     if let Ok(xml) = xmb.to_xml() {
-        let new_xmb = xmb_lib::XmbFile::from_xml(&xml);
+        let (new_xmb, _) = xmb_lib::XmbFile::from_xml(&xml).unwrap();
         assert_eq!(xmb, new_xmb);
     }
 });
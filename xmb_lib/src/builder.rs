@@ -0,0 +1,701 @@
+//! Incremental construction of [Xmb] files.
+use crate::collation::Collation;
+use crate::xmb::{Attribute, Entry, MappedEntry, NamesBuffer, ValuesBuffer, Xmb};
+use crate::XmbFileEntry;
+use indexmap::{IndexMap, IndexSet};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Options for how [XmbBuilder] lays out the [Xmb].
+#[derive(Debug, Default, Clone)]
+pub struct BuildOptions {
+    /// The ordering for the sorted [string_offsets](crate::xmb::Xmb::string_offsets)
+    /// and [mapped_entries](crate::xmb::Xmb::mapped_entries) tables.
+    pub collation: Collation,
+    /// The order of strings in [string_names](crate::xmb::Xmb::string_names)
+    /// and [string_values](crate::xmb::Xmb::string_values).
+    pub string_layout: StringLayout,
+}
+
+/// The arrangement of unique strings in the names and values buffers.
+#[derive(Debug, Default, Clone)]
+pub enum StringLayout {
+    /// Strings in the order they first appear in [entries](crate::xmb::Xmb::entries).
+    #[default]
+    FirstAppearance,
+    /// Strings sorted using [collation](struct.BuildOptions.html#structfield.collation).
+    Sorted,
+    /// Strings in the order of the given names and values from an existing file.
+    /// Strings not in the lists are placed at the end in first appearance order.
+    /// See [StringLayout::original].
+    Original {
+        names: Vec<String>,
+        values: Vec<String>,
+    },
+    /// Values that are a suffix of another value use the end of the longer value
+    /// like `"main"` pointing into `"effect_main"`.
+    /// This minimizes the size of the values buffer.
+    ///
+    /// Names use first appearance order since the names buffer is read using a count of null bytes.
+    SuffixMerged,
+}
+
+impl StringLayout {
+    /// The [StringLayout::Original] layout with the strings in the names and values buffers of `xmb`.
+    pub fn original(xmb: &Xmb) -> Self {
+        let split = |data: &[u8]| {
+            // Ignore the bytes after the last null terminator like alignment padding.
+            let end = data
+                .iter()
+                .rposition(|b| *b == 0)
+                .map(|i| i + 1)
+                .unwrap_or(0);
+            data[..end]
+                .split_inclusive(|b| *b == 0)
+                .map(|s| String::from_utf8_lossy(&s[..s.len() - 1]).into_owned())
+                .collect()
+        };
+        Self::Original {
+            names: split(&xmb.string_names.data),
+            values: split(&xmb.string_values.data),
+        }
+    }
+}
+
+/// Build an [Xmb] from entries in document order.
+///
+/// Each string is stored once regardless of how many entries use it.
+/// This avoids creating an intermediate [XmbFile](crate::XmbFile) for entries from a stream like an XML parser.
+/// # Examples
+/**
+```rust
+use xmb_lib::builder::XmbBuilder;
+
+let mut builder = XmbBuilder::new();
+builder.start_entry("model", [("type", "effect_main")]);
+builder.start_entry("shadow", [("caster", "0")]);
+builder.end_entry();
+builder.end_entry();
+
+let xmb = builder.finish();
+assert_eq!(2, xmb.entry_count);
+```
+ */
+#[derive(Debug, Default)]
+pub struct XmbBuilder<'a> {
+    // Unique names and values shared by all entries.
+    strings: IndexSet<Cow<'a, str>>,
+    nodes: Vec<BuilderNode>,
+    attributes: Vec<(usize, usize)>,
+    roots: Vec<usize>,
+    open_nodes: Vec<usize>,
+    options: BuildOptions,
+}
+
+#[derive(Debug)]
+struct BuilderNode {
+    name: usize,
+    attributes: Range<usize>,
+    children: Vec<usize>,
+}
+
+impl<'a> XmbBuilder<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: BuildOptions) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
+    /// Add an entry as the last child of the current entry or as a root if there is no current entry.
+    /// The new entry becomes the current entry until the matching call to [end_entry](#method.end_entry).
+    pub fn start_entry<N, I, K, V>(&mut self, name: N, attributes: I)
+    where
+        N: Into<Cow<'a, str>>,
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<Cow<'a, str>>,
+        V: Into<Cow<'a, str>>,
+    {
+        let name = self.add_string(name.into());
+
+        let attribute_start = self.attributes.len();
+        for (k, v) in attributes {
+            let k = self.add_string(k.into());
+            let v = self.add_string(v.into());
+            self.attributes.push((k, v));
+        }
+
+        let index = self.nodes.len();
+        self.nodes.push(BuilderNode {
+            name,
+            attributes: attribute_start..self.attributes.len(),
+            children: Vec::new(),
+        });
+
+        match self.open_nodes.last() {
+            Some(parent) => self.nodes[*parent].children.push(index),
+            None => self.roots.push(index),
+        }
+        self.open_nodes.push(index);
+    }
+
+    /// Finish the current entry started by [start_entry](#method.start_entry).
+    ///
+    /// # Panics
+    /// Panics if there is no current entry.
+    pub fn end_entry(&mut self) {
+        self.open_nodes
+            .pop()
+            .expect("end_entry should have a matching start_entry");
+    }
+
+    /// Add `entry` and its children as the last child of the current entry.
+    pub fn add_entry(&mut self, entry: &'a XmbFileEntry) {
+        self.start_entry(&entry.name, &entry.attributes);
+        for child in &entry.children {
+            self.add_entry(child);
+        }
+        self.end_entry();
+    }
+
+    /// Add `entry` and its children as the last child of the current entry.
+    /// This moves the strings instead of copying them like [add_entry](#method.add_entry).
+    pub fn add_owned_entry(&mut self, entry: XmbFileEntry) {
+        self.start_entry(entry.name, entry.attributes);
+        for child in entry.children {
+            self.add_owned_entry(child);
+        }
+        self.end_entry();
+    }
+
+    fn add_string(&mut self, s: Cow<'a, str>) -> usize {
+        // Rust strings allow null bytes but XMB does not.
+        // For now, just strip nulls.
+        let s = if s.contains('\0') {
+            Cow::Owned(s.replace('\0', ""))
+        } else {
+            s
+        };
+        self.strings.insert_full(s).0
+    }
+
+    /// Create the [Xmb] for all added entries.
+    /// Any entries that haven't been ended are treated as ended.
+    pub fn finish(self) -> Xmb {
+        // Flatten the tree by iterating in the expected entry order in the XMB file.
+        let mut flattened_entries = Vec::new();
+        add_flattened_entries_recursive(&self.nodes, &self.roots, &mut flattened_entries, None);
+
+        // Collect unique names and values as they appear in the flattened entries.
+        // TODO: Is this used for some sort of lookup?
+        let mut names = IndexSet::new();
+        let mut values = IndexSet::new();
+        for entry in &flattened_entries {
+            let node = &self.nodes[entry.node];
+            names.insert(node.name);
+            for (k, v) in &self.attributes[node.attributes.clone()] {
+                names.insert(*k);
+                values.insert(*v);
+            }
+        }
+
+        let collation = self.options.collation;
+        let compare = |a: &usize, b: &usize| {
+            collation.compare(self.strings[*a].as_bytes(), self.strings[*b].as_bytes())
+        };
+
+        let (original_names, original_values) = match &self.options.string_layout {
+            StringLayout::Original { names, values } => (names.as_slice(), values.as_slice()),
+            _ => (&[][..], &[][..]),
+        };
+        let name_order = self.string_order(&names, original_names, compare);
+        let value_order = self.string_order(&values, original_values, compare);
+
+        let (names_buffer, name_offsets) = create_string_buffer(&self.strings, &name_order);
+        let (values_buffer, value_offsets) = match self.options.string_layout {
+            StringLayout::SuffixMerged => {
+                create_suffix_merged_string_buffer(&self.strings, &value_order)
+            }
+            _ => create_string_buffer(&self.strings, &value_order),
+        };
+
+        // The offsets are sorted by name to allow binary searching.
+        let mut sorted_names: Vec<_> = names.iter().copied().collect();
+        sorted_names.sort_by(compare);
+        let string_offsets: Vec<_> = sorted_names.iter().map(|i| name_offsets[*i]).collect();
+
+        // Collect strings for id attributes and corresponding node indices.
+        // The lookup is sorted by the "id" value, and the last entry with a value is used.
+        // Assume only the "id" attribute is used for lookups.
+        // This seems to be the case for Smash Ultimate and Smash 4.
+        let id_name = self.strings.get_index_of("id");
+        let mut entry_index_by_id = IndexMap::new();
+        for (i, entry) in flattened_entries.iter().enumerate() {
+            let node = &self.nodes[entry.node];
+            let id_value = self.attributes[node.attributes.clone()]
+                .iter()
+                .find(|(k, _)| Some(*k) == id_name)
+                .map(|(_, v)| *v);
+
+            if let Some(id_value) = id_value {
+                entry_index_by_id.insert(id_value, i);
+            }
+        }
+        entry_index_by_id.sort_by(|a, _, b, _| compare(a, b));
+
+        let mapped_entries: Vec<_> = entry_index_by_id
+            .iter()
+            .map(|(id_value, entry_index)| MappedEntry {
+                value_offset: value_offsets[*id_value],
+                entry_index: *entry_index as u32,
+            })
+            .collect();
+
+        let parent_indices: Vec<_> = flattened_entries.iter().map(|e| e.parent_index).collect();
+        let unk1_values = calculate_unk1_values(&parent_indices);
+
+        let mut entries = Vec::new();
+        let mut attributes = Vec::new();
+        for (entry, unk1) in flattened_entries.iter().zip(unk1_values) {
+            let node = &self.nodes[entry.node];
+
+            let attribute_start_index = if node.attributes.is_empty() {
+                -1
+            } else {
+                attributes.len() as i16
+            };
+
+            entries.push(Entry {
+                name_offset: name_offsets[node.name],
+                attribute_count: node.attributes.len() as u16,
+                child_count: node.children.len() as u16,
+                attribute_start_index,
+                unk1: unk1 as i16,
+                parent_index: entry.parent_index.map(|i| i as i16).unwrap_or(-1),
+                unk2: -1,
+            });
+
+            attributes.extend(
+                self.attributes[node.attributes.clone()]
+                    .iter()
+                    .map(|(k, v)| Attribute {
+                        name_offset: name_offsets[*k],
+                        value_offset: value_offsets[*v],
+                    }),
+            );
+        }
+
+        Xmb {
+            entry_count: entries.len() as u32,
+            attribute_count: attributes.len() as u32,
+            string_count: string_offsets.len() as u32,
+            mapped_entry_count: mapped_entries.len() as u32,
+            string_offsets,
+            entries,
+            attributes,
+            mapped_entries,
+            string_names: NamesBuffer { data: names_buffer },
+            string_values: ValuesBuffer {
+                data: values_buffer,
+            },
+            unks: [0; 5],
+        }
+    }
+
+    // The order of the used string indices in the buffer for the string layout.
+    fn string_order<F>(&self, used: &IndexSet<usize>, original: &[String], compare: F) -> Vec<usize>
+    where
+        F: Fn(&usize, &usize) -> Ordering,
+    {
+        let mut order: Vec<_> = used.iter().copied().collect();
+        match &self.options.string_layout {
+            StringLayout::FirstAppearance | StringLayout::SuffixMerged => (),
+            StringLayout::Sorted => order.sort_by(compare),
+            StringLayout::Original { .. } => {
+                // Use the first occurrence for strings that appear more than once.
+                let mut original_index = HashMap::new();
+                for (i, s) in original.iter().enumerate() {
+                    original_index.entry(s.as_str()).or_insert(i);
+                }
+                order.sort_by_key(|i| {
+                    original_index
+                        .get(&*self.strings[*i])
+                        .copied()
+                        .unwrap_or(usize::MAX)
+                });
+            }
+        }
+        order
+    }
+}
+
+// It makes sense to make the buffers and offsets at the same time.
+// This avoids relying on string length.
+// Returns the buffer and the offset for each string index.
+fn create_string_buffer(strings: &IndexSet<Cow<str>>, order: &[usize]) -> (Vec<u8>, Vec<u32>) {
+    let mut buffer = Vec::new();
+    let mut offsets = vec![0; strings.len()];
+    for i in order {
+        offsets[*i] = buffer.len() as u32;
+
+        // TODO: This writes additional null bytes for empty strings?
+        // TODO: Empty strings won't have a null byte?
+        // TODO: This will only read one null byte when reading a new xmb even if we write more?
+        buffer.extend_from_slice(strings[*i].as_bytes());
+        buffer.push(0u8);
+    }
+    (buffer, offsets)
+}
+
+// Returns the buffer and the offset for each string index
+// with strings that are a suffix of another string sharing its bytes.
+fn create_suffix_merged_string_buffer(
+    strings: &IndexSet<Cow<str>>,
+    order: &[usize],
+) -> (Vec<u8>, Vec<u32>) {
+    // Suffixes are prefixes of the reversed strings.
+    // Sorting the reversed strings places each string before any strings that contain it as a suffix.
+    let reversed = |i: &usize| strings[*i].bytes().rev();
+    let mut by_reversed = order.to_vec();
+    by_reversed.sort_by(|a, b| reversed(a).cmp(reversed(b)));
+
+    // Find the longest string containing each string as a suffix.
+    let mut containing = vec![None; strings.len()];
+    for pair in by_reversed.windows(2).rev() {
+        let (s, next) = (&strings[pair[0]], &strings[pair[1]]);
+        if next.ends_with(&**s) {
+            containing[pair[0]] = Some(containing[pair[1]].unwrap_or(pair[1]));
+        }
+    }
+
+    let containing_order: Vec<_> = order
+        .iter()
+        .copied()
+        .filter(|i| containing[*i].is_none())
+        .collect();
+    let (buffer, mut offsets) = create_string_buffer(strings, &containing_order);
+
+    for i in order {
+        if let Some(c) = containing[*i] {
+            offsets[*i] = offsets[c] + (strings[c].len() - strings[*i].len()) as u32;
+        }
+    }
+    (buffer, offsets)
+}
+
+struct FlattenedEntry {
+    node: usize,
+    parent_index: Option<usize>,
+}
+
+// TODO: Is this just BFS order?
+// Each entry's children are contiguous and appear before the children of the next entry.
+fn add_flattened_entries_recursive(
+    nodes: &[BuilderNode],
+    children: &[usize],
+    flattened_entries: &mut Vec<FlattenedEntry>,
+    parent_index: Option<usize>,
+) {
+    let start = flattened_entries.len();
+    flattened_entries.extend(children.iter().map(|node| FlattenedEntry {
+        node: *node,
+        parent_index,
+    }));
+
+    for (i, node) in children.iter().enumerate() {
+        add_flattened_entries_recursive(
+            nodes,
+            &nodes[*node].children,
+            flattened_entries,
+            Some(start + i),
+        );
+    }
+}
+
+/// Calculate the value of [unk1](crate::xmb::Entry::unk1) for each entry from the parent index of each entry.
+/// Entries are assumed to be in the order they appear in [entries](crate::xmb::Xmb::entries).
+pub(crate) fn calculate_unk1_values(parent_indices: &[Option<usize>]) -> Vec<usize> {
+    let links = EntryLinks::new(parent_indices);
+
+    (0..parent_indices.len())
+        .map(|i| match links.first_child[i] {
+            Some(first_child) => first_child,
+            None => calculate_unk1_leaf(&links, i).unwrap_or(parent_indices.len()),
+        })
+        .collect()
+}
+
+fn calculate_unk1_leaf(links: &EntryLinks, index: usize) -> Option<usize> {
+    // Cover the base case by returning None if there is no parent.
+    // For the rightmost node at the leaf level, this will traverse up the tree.
+    // The root node has no parent and will return None.
+    let mut parent = links.parent[index]?;
+
+    // Limit the iterations in case the parent indices contain a cycle.
+    for _ in 0..links.parent.len() {
+        // TODO: There's a case for some lod.xmb files where this can return -1?
+        match links.parent[parent] {
+            Some(grand_parent) => {
+                // Use the first child of the parent's next sibling.
+                // If this doesn't work, recurse up the tree.
+                let first_child = links.next_sibling[parent].and_then(|s| links.first_child[s]);
+                match first_child {
+                    Some(first_child) => return Some(first_child),
+                    None => parent = grand_parent,
+                }
+            }
+            None => {
+                // It's possible for only some of a node's children to be leaves (no children).
+                // This case comes up in some model.xmb files.
+                return links.next_sibling_first_child[index];
+            }
+        }
+    }
+
+    None
+}
+
+// Precomputed tree links to avoid repeatedly searching the entries.
+struct EntryLinks {
+    parent: Vec<Option<usize>>,
+    first_child: Vec<Option<usize>>,
+    next_sibling: Vec<Option<usize>>,
+    // The first child of the first following sibling with children.
+    next_sibling_first_child: Vec<Option<usize>>,
+}
+
+impl EntryLinks {
+    fn new(parent_indices: &[Option<usize>]) -> Self {
+        let count = parent_indices.len();
+
+        // Invalid parent indices are treated as no parent.
+        let parent: Vec<_> = parent_indices
+            .iter()
+            .map(|p| p.filter(|p| *p < count))
+            .collect();
+
+        let mut children = vec![Vec::new(); count];
+        for (i, p) in parent.iter().enumerate() {
+            if let Some(p) = p {
+                children[*p].push(i);
+            }
+        }
+
+        let first_child: Vec<_> = children.iter().map(|c| c.first().copied()).collect();
+
+        // Entries without a parent aren't considered siblings.
+        let mut next_sibling = vec![None; count];
+        let mut next_sibling_first_child = vec![None; count];
+        for siblings in &children {
+            for pair in siblings.windows(2) {
+                next_sibling[pair[0]] = Some(pair[1]);
+            }
+
+            let mut next_first_child = None;
+            for sibling in siblings.iter().rev() {
+                next_sibling_first_child[*sibling] = next_first_child;
+                if let Some(first_child) = first_child[*sibling] {
+                    next_first_child = Some(first_child);
+                }
+            }
+        }
+
+        Self {
+            parent,
+            first_child,
+            next_sibling,
+            next_sibling_first_child,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{xmb, XmbFile};
+
+    fn build(xmb_file: &XmbFile, options: BuildOptions) -> Xmb {
+        let mut builder = XmbBuilder::with_options(options);
+        for entry in &xmb_file.entries {
+            builder.add_entry(entry);
+        }
+        builder.finish()
+    }
+
+    #[test]
+    fn finish_empty() {
+        let xmb = XmbBuilder::new().finish();
+        assert_eq!(
+            [0, 0, 0, 0],
+            [
+                xmb.entry_count,
+                xmb.attribute_count,
+                xmb.string_count,
+                xmb.mapped_entry_count
+            ]
+        );
+        assert!(xmb.string_names.data.is_empty());
+        assert!(xmb.string_values.data.is_empty());
+        assert!(XmbFile::try_from(&xmb).unwrap().entries.is_empty());
+    }
+
+    #[test]
+    fn finish_open_entries() {
+        let mut builder = XmbBuilder::new();
+        builder.start_entry("a", [("x", "1")]);
+        builder.start_entry("b", Vec::<(&str, &str)>::new());
+        let xmb = builder.finish();
+        assert_eq!(
+            xmb! {
+                a(x = "1") {
+                    b;
+                }
+            },
+            XmbFile::try_from(&xmb).unwrap()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "end_entry should have a matching start_entry")]
+    fn end_entry_without_start_entry() {
+        XmbBuilder::new().end_entry();
+    }
+
+    #[test]
+    fn duplicate_ids() {
+        // The lookup uses the last entry in flattened order for each id.
+        let xmb = build(
+            &xmb! {
+                a(id = "x") {
+                    b {
+                        d(id = "y");
+                    }
+                    c(id = "x");
+                }
+            },
+            BuildOptions::default(),
+        );
+
+        let mapped: Vec<_> = xmb
+            .mapped_entries
+            .iter()
+            .map(|e| (xmb.read_value(e.value_offset).unwrap(), e.entry_index))
+            .collect();
+        assert_eq!(vec![("x".to_string(), 2), ("y".to_string(), 3)], mapped);
+        assert_eq!(2, xmb.mapped_entry_count);
+    }
+
+    #[test]
+    fn null_bytes_removed() {
+        let xmb = build(
+            &xmb! {
+                "a\0b"("x\0" = "1\0");
+            },
+            BuildOptions::default(),
+        );
+        assert_eq!(
+            xmb! {
+                ab(x = "1");
+            },
+            XmbFile::try_from(&xmb).unwrap()
+        );
+    }
+
+    #[test]
+    fn empty_strings() {
+        let xmb_file = xmb! {
+            ""(x = "");
+        };
+        let xmb = build(&xmb_file, BuildOptions::default());
+        assert_eq!(b"\0x\0", &xmb.string_names.data[..]);
+        assert_eq!(b"\0", &xmb.string_values.data[..]);
+        assert_eq!(xmb_file, XmbFile::try_from(&xmb).unwrap());
+    }
+
+    #[test]
+    fn suffix_merged_values() {
+        // Empty strings and repeated suffixes should share the longest value.
+        let xmb_file = xmb! {
+            a(b = "main", c = "effect_main", d = "", e = "n") {
+                a(b = "main");
+            }
+        };
+        let xmb = build(
+            &xmb_file,
+            BuildOptions {
+                string_layout: StringLayout::SuffixMerged,
+                ..Default::default()
+            },
+        );
+        assert_eq!(b"effect_main\0", &xmb.string_values.data[..]);
+        assert_eq!(
+            vec![7, 0, 11, 10, 7],
+            xmb.attributes
+                .iter()
+                .map(|a| a.value_offset)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(xmb_file, XmbFile::try_from(&xmb).unwrap());
+    }
+
+    #[test]
+    fn original_layout_split() {
+        let mut xmb = XmbBuilder::new().finish();
+        let StringLayout::Original { names, values } = StringLayout::original(&xmb) else {
+            panic!()
+        };
+        assert!(names.is_empty() && values.is_empty());
+
+        // Bytes after the last null terminator aren't a complete string.
+        xmb.string_names.data = b"a\0b".to_vec();
+        xmb.string_values.data = b"a\0\0".to_vec();
+        let StringLayout::Original { names, values } = StringLayout::original(&xmb) else {
+            panic!()
+        };
+        assert_eq!(vec!["a"], names);
+        assert_eq!(vec!["a", ""], values);
+    }
+
+    #[test]
+    fn original_layout_missing_strings() {
+        // Strings not in the original layout are placed at the end.
+        let xmb = build(
+            &xmb! {
+                a(b = "1", c = "2");
+            },
+            BuildOptions {
+                string_layout: StringLayout::Original {
+                    names: vec!["c".to_string(), "unused".to_string(), "c".to_string()],
+                    values: vec!["2".to_string()],
+                },
+                ..Default::default()
+            },
+        );
+        assert_eq!(b"c\0a\0b\0", &xmb.string_names.data[..]);
+        assert_eq!(b"2\x001\0", &xmb.string_values.data[..]);
+    }
+
+    #[test]
+    fn unk1_invalid_parents() {
+        assert!(calculate_unk1_values(&[]).is_empty());
+
+        // Invalid parents are treated as no parent.
+        assert_eq!(
+            calculate_unk1_values(&[None, None]),
+            calculate_unk1_values(&[None, Some(5)])
+        );
+
+        // Cycles in the parent indices shouldn't loop forever.
+        assert_eq!(
+            vec![3, 2, 1],
+            calculate_unk1_values(&[None, Some(2), Some(1)])
+        );
+        assert_eq!(vec![0], calculate_unk1_values(&[Some(0)]));
+    }
+}
@@ -0,0 +1,253 @@
+//! Normalizing differences that don't affect the meaning of an [XmbFile].
+//!
+//! The derived [PartialEq] for [XmbFile] compares values as exact strings
+//! and ignores the order of attributes since attributes use an [IndexMap](indexmap::IndexMap).
+//! [XmbFile::semantic_eq] compares the canonical forms instead
+//! to check if a rebuilt file is equivalent to the original.
+//!
+//! Values for the `"id"` attribute are never normalized since the
+//! [mapped_entries](crate::xmb::Xmb::mapped_entries) lookup compares the exact strings.
+use crate::index::ID_ATTRIBUTE;
+use crate::{XmbFile, XmbFileEntry};
+
+/// Options for [XmbFile::canonicalize] and [XmbFile::semantic_eq].
+/// All options except [normalize_numbers](#structfield.normalize_numbers) are enabled by default.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct CanonicalOptions {
+    /// Sort attributes by name instead of preserving the original order.
+    pub sort_attributes: bool,
+    /// Write numeric values in a consistent format like `"1.50"` to `"1.5"` and `"+007"` to `"7"`.
+    /// Floating point values with the same value after parsing are written the same way.
+    ///
+    /// This is disabled by default since the game may compare some values as strings.
+    pub normalize_numbers: bool,
+    /// Remove leading and trailing whitespace and replace other whitespace with a single space.
+    pub normalize_whitespace: bool,
+}
+
+impl Default for CanonicalOptions {
+    fn default() -> Self {
+        Self {
+            sort_attributes: true,
+            normalize_numbers: false,
+            normalize_whitespace: true,
+        }
+    }
+}
+
+impl XmbFile {
+    /// Convert all entries to a canonical form based on `options`.
+    /// Entry names and the order of entries are not changed.
+    pub fn canonicalize(&mut self, options: &CanonicalOptions) {
+        for entry in &mut self.entries {
+            canonicalize_entry(entry, options);
+        }
+    }
+
+    /// Returns `true` if both files are equal after calling [canonicalize](#method.canonicalize).
+    /// Unlike [PartialEq], the order of attributes is compared unless
+    /// [sort_attributes](struct.CanonicalOptions.html#structfield.sort_attributes) is enabled.
+    pub fn semantic_eq(&self, other: &XmbFile, options: &CanonicalOptions) -> bool {
+        let mut a = self.clone();
+        a.canonicalize(options);
+        let mut b = other.clone();
+        b.canonicalize(options);
+        entries_eq(&a.entries, &b.entries)
+    }
+}
+
+fn canonicalize_entry(entry: &mut XmbFileEntry, options: &CanonicalOptions) {
+    for (name, value) in entry.attributes.iter_mut() {
+        if name == ID_ATTRIBUTE {
+            continue;
+        }
+        if options.normalize_whitespace {
+            *value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+        }
+        if options.normalize_numbers {
+            if let Some(number) = normalize_number(value) {
+                *value = number;
+            }
+        }
+    }
+
+    if options.sort_attributes {
+        entry.attributes.sort_keys();
+    }
+
+    for child in &mut entry.children {
+        canonicalize_entry(child, options);
+    }
+}
+
+fn entries_eq(a: &[XmbFileEntry], b: &[XmbFileEntry]) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).all(|(a, b)| {
+            a.name == b.name
+                && a.attributes.iter().eq(&b.attributes)
+                && entries_eq(&a.children, &b.children)
+        })
+}
+
+fn normalize_number(value: &str) -> Option<String> {
+    // Avoid parsing special values like "inf" or "NaN" that are likely meant as text.
+    let is_numeric = value.bytes().any(|b| b.is_ascii_digit())
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_digit() || matches!(b, b'+' | b'-' | b'.' | b'e' | b'E'));
+    if !is_numeric {
+        return None;
+    }
+
+    // Parse integers separately to avoid losing precision for large values.
+    if !value.contains(['.', 'e', 'E']) {
+        return value.parse::<i64>().ok().map(|i| i.to_string());
+    }
+
+    let f: f64 = value.parse().ok()?;
+    if f == 0.0 {
+        // Treat "-0.0" and "0.0" as the same value.
+        Some("0".to_string())
+    } else {
+        f.is_finite().then(|| f.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xmb_file_semantic_eq() {
+        let a = XmbFile {
+            entries: vec![XmbFileEntry::new("param")
+                .attr("scale", "1.50")
+                .attr("count", "+007")
+                .attr("name", " eff  elec ")
+                .child(
+                    XmbFileEntry::new("color")
+                        .attr("r", "-0.0")
+                        .attr("g", "1e0"),
+                )],
+        };
+        let b = XmbFile {
+            entries: vec![XmbFileEntry::new("param")
+                .attr("name", "eff elec")
+                .attr("count", "7")
+                .attr("scale", "1.5")
+                .child(XmbFileEntry::new("color").attr("g", "1").attr("r", "0"))],
+        };
+
+        let options = CanonicalOptions {
+            normalize_numbers: true,
+            ..Default::default()
+        };
+        assert_ne!(a, b);
+        assert!(a.semantic_eq(&b, &options));
+
+        let mut canonical = a.clone();
+        canonical.canonicalize(&options);
+        assert_eq!(
+            XmbFile {
+                entries: vec![XmbFileEntry::new("param")
+                    .attr("count", "7")
+                    .attr("name", "eff elec")
+                    .attr("scale", "1.5")
+                    .child(XmbFileEntry::new("color").attr("g", "1").attr("r", "0"))],
+            },
+            canonical
+        );
+        assert_eq!(
+            vec!["count", "name", "scale"],
+            canonical.entries[0].attributes.keys().collect::<Vec<_>>()
+        );
+
+        for other_options in [
+            CanonicalOptions {
+                sort_attributes: false,
+                ..options
+            },
+            CanonicalOptions::default(),
+            CanonicalOptions {
+                normalize_whitespace: false,
+                ..options
+            },
+        ] {
+            assert!(!a.semantic_eq(&b, &other_options));
+        }
+
+        // Text that happens to parse as a number should be preserved.
+        let c = XmbFile {
+            entries: vec![XmbFileEntry::new("param").attr("a", "inf").attr("b", "1-2")],
+        };
+        let mut canonical = c.clone();
+        canonical.canonicalize(&options);
+        assert_eq!(c, canonical);
+
+        // The order of entries is significant.
+        let d = XmbFile {
+            entries: vec![XmbFileEntry::new("a"), XmbFileEntry::new("b")],
+        };
+        let e = XmbFile {
+            entries: vec![XmbFileEntry::new("b"), XmbFileEntry::new("a")],
+        };
+        assert!(!d.semantic_eq(&e, &options));
+    }
+
+    #[test]
+    fn semantic_eq_ids() {
+        // Ids are compared exactly like the mapped entries lookup.
+        let options = CanonicalOptions {
+            normalize_numbers: true,
+            ..Default::default()
+        };
+        let a = XmbFile {
+            entries: vec![XmbFileEntry::new("a").attr("id", "01").attr("x", "01")],
+        };
+        let b = XmbFile {
+            entries: vec![XmbFileEntry::new("a").attr("id", "1").attr("x", "1")],
+        };
+        assert!(!a.semantic_eq(&b, &options));
+
+        let c = XmbFile {
+            entries: vec![XmbFileEntry::new("a").attr("id", " 1").attr("x", " 1")],
+        };
+        let d = XmbFile {
+            entries: vec![XmbFileEntry::new("a").attr("id", "1").attr("x", "1")],
+        };
+        assert!(!c.semantic_eq(&d, &options));
+
+        let mut canonical = a.clone();
+        canonical.canonicalize(&options);
+        assert_eq!(Some("01"), canonical.entries[0].get_attr("id"));
+        assert_eq!(Some("1"), canonical.entries[0].get_attr("x"));
+    }
+
+    #[test]
+    fn semantic_eq_default_numbers() {
+        let a = XmbFile {
+            entries: vec![XmbFileEntry::new("a").attr("x", "1.50")],
+        };
+        let b = XmbFile {
+            entries: vec![XmbFileEntry::new("a").attr("x", "1.5")],
+        };
+        assert!(!a.semantic_eq(&b, &CanonicalOptions::default()));
+    }
+
+    #[test]
+    fn canonicalize_empty() {
+        let mut xmb_file = XmbFile {
+            entries: vec![XmbFileEntry::new("a").attr("x", "").attr("y", "   ")],
+        };
+        xmb_file.canonicalize(&CanonicalOptions {
+            normalize_numbers: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            XmbFile {
+                entries: vec![XmbFileEntry::new("a").attr("x", "").attr("y", "")],
+            },
+            xmb_file
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! Orderings for the sorted [string_offsets](crate::xmb::Xmb::string_offsets)
+//! and [mapped_entries](crate::xmb::Xmb::mapped_entries) tables.
+//!
+//! The game likely uses a binary search on these tables,
+//! so rebuilt files should sort strings with the same comparison as the game.
+use crate::events::read_string_bytes;
+use crate::xmb::Xmb;
+use crate::ReadXmbError;
+use std::cmp::Ordering;
+
+/// A comparison for sorting strings by their bytes in [Xmb] lookup tables.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Collation {
+    /// Compare bytes as unsigned values like `strcmp` with unsigned chars.
+    /// This is the same as comparing [str] values in Rust.
+    #[default]
+    Bytes,
+    /// Compare bytes as signed values like `strcmp` with signed chars.
+    /// This only differs from [Collation::Bytes] for non ASCII strings.
+    SignedBytes,
+    /// Compare bytes after converting ASCII letters to lowercase like `_stricmp`.
+    CaseInsensitive,
+    /// Compare shorter strings first and then compare bytes for strings with the same length.
+    LengthFirst,
+    /// A custom comparison for orderings not covered by the other variants.
+    Custom(fn(&[u8], &[u8]) -> Ordering),
+}
+
+impl Collation {
+    /// The collations with predefined comparisons checked by [Xmb::detect_collations].
+    pub const BUILTIN: [Collation; 4] = [
+        Collation::Bytes,
+        Collation::SignedBytes,
+        Collation::CaseInsensitive,
+        Collation::LengthFirst,
+    ];
+
+    /// Compare the bytes of two strings without the null terminator.
+    pub fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        match self {
+            Collation::Bytes => a.cmp(b),
+            Collation::SignedBytes => a.iter().map(|c| *c as i8).cmp(b.iter().map(|c| *c as i8)),
+            Collation::CaseInsensitive => a
+                .iter()
+                .map(u8::to_ascii_lowercase)
+                .cmp(b.iter().map(u8::to_ascii_lowercase)),
+            Collation::LengthFirst => a.len().cmp(&b.len()).then_with(|| a.cmp(b)),
+            Collation::Custom(compare) => compare(a, b),
+        }
+    }
+
+    fn is_sorted<'a, I: IntoIterator<Item = &'a [u8]>>(&self, strings: I) -> bool {
+        let mut strings = strings.into_iter();
+        let Some(mut previous) = strings.next() else {
+            return true;
+        };
+        strings.all(|s| {
+            let is_ordered = self.compare(previous, s) != Ordering::Greater;
+            previous = s;
+            is_ordered
+        })
+    }
+}
+
+impl Xmb {
+    /// Find the [Collation::BUILTIN] collations that sort both
+    /// [string_offsets](#structfield.string_offsets) and [mapped_entries](#structfield.mapped_entries).
+    ///
+    /// More than one collation can match since collations often agree for short or lowercase strings.
+    /// An empty list means the tables use an unknown ordering or aren't sorted.
+    pub fn detect_collations(&self) -> Result<Vec<Collation>, ReadXmbError> {
+        let names = self
+            .string_offsets
+            .iter()
+            .map(|o| {
+                read_string_bytes(&self.string_names.data, *o)
+                    .ok_or(ReadXmbError::InvalidString(*o))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let ids = self
+            .mapped_entries
+            .iter()
+            .map(|e| {
+                read_string_bytes(&self.string_values.data, e.value_offset)
+                    .ok_or(ReadXmbError::InvalidString(e.value_offset))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Collation::BUILTIN
+            .into_iter()
+            .filter(|c| c.is_sorted(names.iter().copied()) && c.is_sorted(ids.iter().copied()))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xmb;
+    use crate::xmb::MappedEntry;
+
+    #[test]
+    fn compare_empty_and_prefix() {
+        for collation in Collation::BUILTIN {
+            assert_eq!(Ordering::Equal, collation.compare(b"", b""));
+            assert_eq!(Ordering::Less, collation.compare(b"", b"a"));
+            assert_eq!(Ordering::Less, collation.compare(b"a", b"ab"));
+            assert_eq!(Ordering::Greater, collation.compare(b"ab", b"a"));
+        }
+    }
+
+    #[test]
+    fn compare_case_insensitive() {
+        let collation = Collation::CaseInsensitive;
+        assert_eq!(Ordering::Equal, collation.compare(b"ABC", b"abc"));
+        assert_eq!(Ordering::Less, collation.compare(b"B", b"c"));
+        // Only ASCII letters are converted.
+        assert_eq!(
+            Ordering::Less,
+            collation.compare("\u{c9}".as_bytes(), "\u{e9}".as_bytes())
+        );
+    }
+
+    #[test]
+    fn is_sorted_duplicates() {
+        assert!(Collation::Bytes.is_sorted(Vec::<&[u8]>::new()));
+        assert!(Collation::Bytes.is_sorted([&b"a"[..]]));
+        assert!(Collation::Bytes.is_sorted([&b"a"[..], b"a", b"b"]));
+        assert!(!Collation::Bytes.is_sorted([&b"b"[..], b"a"]));
+    }
+
+    #[test]
+    fn detect_collations_empty() {
+        // Empty tables are sorted for every collation.
+        let xmb = Xmb::from(&xmb! {});
+        assert_eq!(4, xmb.detect_collations().unwrap().len());
+    }
+
+    #[test]
+    fn detect_collations_invalid_offsets() {
+        let mut xmb = Xmb::from(&xmb! {
+            a(id = "b");
+        });
+        xmb.mapped_entries.push(MappedEntry {
+            value_offset: 100,
+            entry_index: 0,
+        });
+        assert!(matches!(
+            xmb.detect_collations(),
+            Err(ReadXmbError::InvalidString(100))
+        ));
+
+        xmb.string_offsets.push(50);
+        assert!(matches!(
+            xmb.detect_collations(),
+            Err(ReadXmbError::InvalidString(50))
+        ));
+    }
+
+    #[test]
+    fn detect_collations_unsorted() {
+        let mut xmb = Xmb::from(&xmb! {
+            a(b = "c");
+        });
+        xmb.string_offsets.reverse();
+        assert!(xmb.detect_collations().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,500 @@
+//! An editable tree of nodes with parent and sibling links.
+//!
+//! [XmbFileEntry] owns its children, which makes finding the parent of an entry
+//! or moving an entry to a different parent require searching the tree.
+//! [XmbDocument] stores all nodes in a single list and refers to nodes by [NodeId] instead.
+//! Ids remain valid after moving or removing other nodes.
+//!
+//! # Examples
+/*!
+```rust
+use xmb_lib::document::XmbDocument;
+
+let mut document = XmbDocument::new();
+let root = document.append_root("root");
+let a = document.append_child(root, "a").unwrap();
+let b = document.append_child(root, "b").unwrap();
+
+// Move b to be a child of a.
+document.move_to(b, Some(a)).unwrap();
+assert_eq!(Some(a), document.node(b).unwrap().parent());
+
+// Nodes can't be moved into one of their descendants.
+assert!(document.move_to(a, Some(b)).is_err());
+```
+*/
+use crate::xmb::Xmb;
+use crate::{ReadXmbError, XmbFile, XmbFileEntry};
+use indexmap::IndexMap;
+use thiserror::Error;
+
+/// A handle to a node in an [XmbDocument].
+///
+/// For documents converted from [XmbFile] or [Xmb],
+/// the [index](#method.index) is the index in [entries](crate::xmb::Xmb::entries).
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    /// The id for the node at `index` like the node for an entry index in a converted [Xmb].
+    /// Methods return `None` or an error if the node doesn't exist.
+    pub fn from_index(index: usize) -> Self {
+        Self(index)
+    }
+
+    /// The position of the node in the order it was added to the document.
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum DocumentError {
+    #[error("node {0:?} does not exist or was removed")]
+    NodeNotFound(NodeId),
+
+    #[error("cannot move node {node:?} into itself or one of its descendants")]
+    MoveIntoDescendant { node: NodeId, target: NodeId },
+}
+
+/// A node in an [XmbDocument] corresponding to an [XmbFileEntry].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct XmbNode {
+    pub name: String,
+    pub attributes: IndexMap<String, String>,
+    parent: Option<NodeId>,
+    first_child: Option<NodeId>,
+    last_child: Option<NodeId>,
+    previous_sibling: Option<NodeId>,
+    next_sibling: Option<NodeId>,
+}
+
+impl XmbNode {
+    fn new(name: String, attributes: IndexMap<String, String>) -> Self {
+        Self {
+            name,
+            attributes,
+            parent: None,
+            first_child: None,
+            last_child: None,
+            previous_sibling: None,
+            next_sibling: None,
+        }
+    }
+
+    /// The parent node or `None` for root nodes.
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn first_child(&self) -> Option<NodeId> {
+        self.first_child
+    }
+
+    pub fn last_child(&self) -> Option<NodeId> {
+        self.last_child
+    }
+
+    pub fn previous_sibling(&self) -> Option<NodeId> {
+        self.previous_sibling
+    }
+
+    pub fn next_sibling(&self) -> Option<NodeId> {
+        self.next_sibling
+    }
+}
+
+/// An editable tree of nodes corresponding to an [XmbFile].
+/// See the [module docs](crate::document) for details.
+#[derive(Debug, Default, Clone)]
+pub struct XmbDocument {
+    // Removed nodes are set to None to keep the remaining ids valid.
+    nodes: Vec<Option<XmbNode>>,
+    first_root: Option<NodeId>,
+    last_root: Option<NodeId>,
+}
+
+impl XmbDocument {
+    /// Create a document with no nodes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The node for `id` or `None` if the node was removed.
+    pub fn node(&self, id: NodeId) -> Option<&XmbNode> {
+        self.nodes.get(id.0)?.as_ref()
+    }
+
+    /// The node for `id` or `None` if the node was removed.
+    pub fn node_mut(&mut self, id: NodeId) -> Option<&mut XmbNode> {
+        self.nodes.get_mut(id.0)?.as_mut()
+    }
+
+    /// The nodes without a parent in order.
+    pub fn roots(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.siblings(self.first_root)
+    }
+
+    /// The children of `id` in order or an empty iterator if the node was removed.
+    pub fn children(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.siblings(self.node(id).and_then(|n| n.first_child))
+    }
+
+    /// Add a node with no attributes after the last root node.
+    pub fn append_root<S: Into<String>>(&mut self, name: S) -> NodeId {
+        let id = self.push_node(XmbNode::new(name.into(), IndexMap::new()));
+        self.link_last(id, None);
+        id
+    }
+
+    /// Add a node with no attributes after the last child of `parent`.
+    pub fn append_child<S: Into<String>>(
+        &mut self,
+        parent: NodeId,
+        name: S,
+    ) -> Result<NodeId, DocumentError> {
+        self.check_node(parent)?;
+        let id = self.push_node(XmbNode::new(name.into(), IndexMap::new()));
+        self.link_last(id, Some(parent));
+        Ok(id)
+    }
+
+    /// Move `id` and its descendants after the last child of `parent`
+    /// or after the last root node if `parent` is `None`.
+    pub fn move_to(&mut self, id: NodeId, parent: Option<NodeId>) -> Result<(), DocumentError> {
+        self.check_node(id)?;
+        if let Some(parent) = parent {
+            self.check_move(id, parent)?;
+        }
+
+        self.unlink(id);
+        self.link_last(id, parent);
+        Ok(())
+    }
+
+    /// Move `id` and its descendants to be the previous sibling of `sibling`.
+    pub fn move_before(&mut self, id: NodeId, sibling: NodeId) -> Result<(), DocumentError> {
+        self.check_node(id)?;
+        self.check_move(id, sibling)?;
+
+        self.unlink(id);
+
+        let parent = self.nodes[sibling.0].as_ref().unwrap().parent;
+        let previous = self.nodes[sibling.0].as_ref().unwrap().previous_sibling;
+        {
+            let node = self.nodes[id.0].as_mut().unwrap();
+            node.parent = parent;
+            node.previous_sibling = previous;
+            node.next_sibling = Some(sibling);
+        }
+        self.nodes[sibling.0].as_mut().unwrap().previous_sibling = Some(id);
+        match previous {
+            Some(previous) => self.nodes[previous.0].as_mut().unwrap().next_sibling = Some(id),
+            None => *self.first_link(parent) = Some(id),
+        }
+        Ok(())
+    }
+
+    /// Remove `id` and its descendants from the document.
+    /// The ids of the removed nodes are no longer valid.
+    pub fn remove(&mut self, id: NodeId) -> Result<XmbNode, DocumentError> {
+        self.check_node(id)?;
+        self.unlink(id);
+
+        let mut descendants: Vec<_> = self.children(id).collect();
+        while let Some(child) = descendants.pop() {
+            descendants.extend(self.children(child));
+            self.nodes[child.0] = None;
+        }
+
+        let mut node = self.nodes[id.0].take().unwrap();
+        node.first_child = None;
+        node.last_child = None;
+        Ok(node)
+    }
+
+    /// Convert the document to entries.
+    pub fn to_xmb_file(&self) -> XmbFile {
+        XmbFile {
+            entries: self.roots().map(|id| self.create_entry(id)).collect(),
+        }
+    }
+
+    fn create_entry(&self, id: NodeId) -> XmbFileEntry {
+        let node = self.nodes[id.0].as_ref().unwrap();
+        XmbFileEntry {
+            name: node.name.clone(),
+            attributes: node.attributes.clone(),
+            children: self.children(id).map(|c| self.create_entry(c)).collect(),
+        }
+    }
+
+    fn siblings(&self, first: Option<NodeId>) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(first, |id| self.nodes[id.0].as_ref()?.next_sibling)
+    }
+
+    fn push_node(&mut self, node: XmbNode) -> NodeId {
+        self.nodes.push(Some(node));
+        NodeId(self.nodes.len() - 1)
+    }
+
+    fn check_node(&self, id: NodeId) -> Result<(), DocumentError> {
+        self.node(id)
+            .map(|_| ())
+            .ok_or(DocumentError::NodeNotFound(id))
+    }
+
+    // Check that target exists and is not id or a descendant of id.
+    fn check_move(&self, id: NodeId, target: NodeId) -> Result<(), DocumentError> {
+        self.check_node(target)?;
+        let is_descendant =
+            std::iter::successors(Some(target), |n| self.nodes[n.0].as_ref()?.parent)
+                .any(|n| n == id);
+        if is_descendant {
+            Err(DocumentError::MoveIntoDescendant { node: id, target })
+        } else {
+            Ok(())
+        }
+    }
+
+    fn first_link(&mut self, parent: Option<NodeId>) -> &mut Option<NodeId> {
+        match parent {
+            Some(parent) => &mut self.nodes[parent.0].as_mut().unwrap().first_child,
+            None => &mut self.first_root,
+        }
+    }
+
+    fn last_link(&mut self, parent: Option<NodeId>) -> &mut Option<NodeId> {
+        match parent {
+            Some(parent) => &mut self.nodes[parent.0].as_mut().unwrap().last_child,
+            None => &mut self.last_root,
+        }
+    }
+
+    // Add an unlinked node after the last sibling.
+    fn link_last(&mut self, id: NodeId, parent: Option<NodeId>) {
+        let last = self.last_link(parent).replace(id);
+        {
+            let node = self.nodes[id.0].as_mut().unwrap();
+            node.parent = parent;
+            node.previous_sibling = last;
+            node.next_sibling = None;
+        }
+        match last {
+            Some(last) => self.nodes[last.0].as_mut().unwrap().next_sibling = Some(id),
+            None => *self.first_link(parent) = Some(id),
+        }
+    }
+
+    // Remove a node from its siblings without removing its children.
+    fn unlink(&mut self, id: NodeId) {
+        let node = self.nodes[id.0].as_mut().unwrap();
+        let parent = node.parent.take();
+        let previous = node.previous_sibling.take();
+        let next = node.next_sibling.take();
+
+        match previous {
+            Some(previous) => self.nodes[previous.0].as_mut().unwrap().next_sibling = next,
+            None => *self.first_link(parent) = next,
+        }
+        match next {
+            Some(next) => self.nodes[next.0].as_mut().unwrap().previous_sibling = previous,
+            None => *self.last_link(parent) = previous,
+        }
+    }
+
+    // Add siblings before their children to match the flattened order used when building an Xmb.
+    fn add_entries_recursive(&mut self, entries: &[XmbFileEntry], parent: Option<NodeId>) {
+        let ids: Vec<_> = entries
+            .iter()
+            .map(|e| {
+                let id = self.push_node(XmbNode::new(e.name.clone(), e.attributes.clone()));
+                self.link_last(id, parent);
+                id
+            })
+            .collect();
+
+        for (entry, id) in entries.iter().zip(ids) {
+            self.add_entries_recursive(&entry.children, Some(id));
+        }
+    }
+}
+
+impl From<&XmbFile> for XmbDocument {
+    fn from(xmb_file: &XmbFile) -> Self {
+        let mut document = Self::new();
+        document.add_entries_recursive(&xmb_file.entries, None);
+        document
+    }
+}
+
+impl From<&XmbDocument> for XmbFile {
+    fn from(document: &XmbDocument) -> Self {
+        document.to_xmb_file()
+    }
+}
+
+impl TryFrom<&Xmb> for XmbDocument {
+    type Error = ReadXmbError;
+
+    fn try_from(xmb: &Xmb) -> Result<Self, Self::Error> {
+        XmbFile::try_from(xmb).map(|f| Self::from(&f))
+    }
+}
+
+impl From<&XmbDocument> for Xmb {
+    fn from(document: &XmbDocument) -> Self {
+        Xmb::from(&document.to_xmb_file())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xmb;
+
+    #[test]
+    fn empty_document() {
+        let document = XmbDocument::new();
+        assert_eq!(0, document.roots().count());
+        assert!(document.node(NodeId::from_index(0)).is_none());
+        assert_eq!(0, document.children(NodeId::from_index(0)).count());
+        assert!(document.to_xmb_file().entries.is_empty());
+        assert!(XmbDocument::from(&document.to_xmb_file())
+            .to_xmb_file()
+            .entries
+            .is_empty());
+    }
+
+    #[test]
+    fn missing_nodes() {
+        let mut document = XmbDocument::new();
+        let root = document.append_root("root");
+        let missing = NodeId::from_index(5);
+
+        assert!(matches!(
+            document.append_child(missing, "a"),
+            Err(DocumentError::NodeNotFound(id)) if id == missing
+        ));
+        assert!(matches!(
+            document.move_to(missing, Some(root)),
+            Err(DocumentError::NodeNotFound(_))
+        ));
+        assert!(matches!(
+            document.move_to(root, Some(missing)),
+            Err(DocumentError::NodeNotFound(_))
+        ));
+        assert!(matches!(
+            document.move_before(root, missing),
+            Err(DocumentError::NodeNotFound(_))
+        ));
+        assert!(matches!(
+            document.remove(missing),
+            Err(DocumentError::NodeNotFound(_))
+        ));
+
+        // Removed ids aren't reused.
+        document.remove(root).unwrap();
+        assert!(matches!(
+            document.remove(root),
+            Err(DocumentError::NodeNotFound(_))
+        ));
+        let new_root = document.append_root("root");
+        assert_ne!(root, new_root);
+        assert!(document.node(root).is_none());
+    }
+
+    #[test]
+    fn move_into_self() {
+        let mut document = XmbDocument::new();
+        let root = document.append_root("root");
+        let a = document.append_child(root, "a").unwrap();
+
+        assert!(matches!(
+            document.move_to(root, Some(root)),
+            Err(DocumentError::MoveIntoDescendant { node, target }) if node == root && target == root
+        ));
+        assert!(matches!(
+            document.move_before(root, a),
+            Err(DocumentError::MoveIntoDescendant { .. })
+        ));
+
+        // Failed moves shouldn't change the document.
+        assert_eq!(
+            xmb! {
+                root {
+                    a;
+                }
+            },
+            document.to_xmb_file()
+        );
+    }
+
+    #[test]
+    fn move_siblings() {
+        let mut document = XmbDocument::new();
+        let a = document.append_root("a");
+        let b = document.append_root("b");
+        let c = document.append_root("c");
+
+        // Moving to the same parent moves to the end.
+        document.move_to(a, None).unwrap();
+        assert_eq!(xmb! { b; c; a; }, document.to_xmb_file());
+
+        document.move_before(a, b).unwrap();
+        assert_eq!(xmb! { a; b; c; }, document.to_xmb_file());
+
+        document.move_before(c, a).unwrap();
+        assert_eq!(xmb! { c; a; b; }, document.to_xmb_file());
+        assert_eq!(None, document.node(c).unwrap().previous_sibling());
+        assert_eq!(Some(b), document.node(a).unwrap().next_sibling());
+        assert_eq!(None, document.node(b).unwrap().next_sibling());
+    }
+
+    #[test]
+    fn remove_relinks_siblings() {
+        let mut document = XmbDocument::new();
+        let root = document.append_root("root");
+        let a = document.append_child(root, "a").unwrap();
+        let b = document.append_child(root, "b").unwrap();
+        let c = document.append_child(root, "c").unwrap();
+        let d = document.append_child(b, "d").unwrap();
+
+        document.remove(b).unwrap();
+        assert!(document.node(d).is_none());
+        assert_eq!(
+            xmb! {
+                root {
+                    a;
+                    c;
+                }
+            },
+            document.to_xmb_file()
+        );
+        assert_eq!(Some(c), document.node(a).unwrap().next_sibling());
+        assert_eq!(Some(a), document.node(c).unwrap().previous_sibling());
+
+        document.remove(a).unwrap();
+        document.remove(c).unwrap();
+        let node = document.node(root).unwrap();
+        assert_eq!((None, None), (node.first_child(), node.last_child()));
+    }
+
+    #[test]
+    fn from_xmb_invalid_parents() {
+        let mut xmb = Xmb::from(&xmb! {
+            a {
+                b;
+                c;
+                d;
+            }
+        });
+
+        // Entries with cyclic or invalid parents aren't reachable from the roots.
+        xmb.entries[1].parent_index = 2;
+        xmb.entries[2].parent_index = 1;
+        xmb.entries[3].parent_index = 10;
+        let document = XmbDocument::try_from(&xmb).unwrap();
+        assert_eq!(xmb! { a; }, document.to_xmb_file());
+    }
+}
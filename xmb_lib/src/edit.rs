@@ -0,0 +1,372 @@
+//! Editing an [Xmb] directly while preserving the existing layout.
+//!
+//! Converting to [XmbFile](crate::XmbFile) and back rebuilds the entry order, string buffers, and links.
+//! These methods only change the data needed for the edit.
+//! New strings are added to the end of the string buffers, and existing strings are never moved,
+//! so all other offsets remain valid.
+//! Strings that are no longer used are left in the buffers.
+//!
+//! New rows in the [string_offsets](crate::xmb::Xmb::string_offsets) and [mapped_entries](crate::xmb::Xmb::mapped_entries)
+//! tables are inserted in sorted order with the first collation from [Xmb::detect_collations]
+//! or the default [Collation] if the existing tables don't use a known ordering.
+//! The other rows are never reordered or removed, so tables with duplicate ids keep their existing rows.
+use crate::collation::Collation;
+use crate::events::read_string_bytes;
+use crate::index::ID_ATTRIBUTE;
+use crate::xmb::{Attribute, MappedEntry, Xmb};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum EditXmbError {
+    #[error("entry index {0} is out of range")]
+    InvalidEntry(usize),
+
+    #[error("entry {entry} has no attribute {name:?}")]
+    MissingAttribute { entry: usize, name: String },
+
+    #[error("entry {entry} already has an attribute {name:?}")]
+    DuplicateAttribute { entry: usize, name: String },
+
+    #[error("string {0:?} contains a null byte")]
+    NullByte(String),
+
+    #[error("adding more attributes would exceed the maximum attribute index")]
+    TooManyAttributes,
+}
+
+impl Xmb {
+    /// Set the value of the attribute `name` for the entry at `entry_index`.
+    /// Changing the `"id"` attribute also moves the row for the entry in [mapped_entries](#structfield.mapped_entries).
+    pub fn set_attribute_value(
+        &mut self,
+        entry_index: usize,
+        name: &str,
+        value: &str,
+    ) -> Result<(), EditXmbError> {
+        check_string(value)?;
+        let attribute_index = self
+            .attribute_indices(entry_index)?
+            .find(|i| {
+                read_string_bytes(&self.string_names.data, self.attributes[*i].name_offset)
+                    == Some(name.as_bytes())
+            })
+            .ok_or_else(|| EditXmbError::MissingAttribute {
+                entry: entry_index,
+                name: name.to_string(),
+            })?;
+
+        let value_offset = self.find_or_add_value(value);
+        self.attributes[attribute_index].value_offset = value_offset;
+        if name == ID_ATTRIBUTE {
+            let collation = self.edit_collation();
+            self.update_mapped_entry(entry_index, value_offset, collation);
+        }
+        Ok(())
+    }
+
+    /// Change the name of the entry at `entry_index` to `name`.
+    pub fn rename_entry(&mut self, entry_index: usize, name: &str) -> Result<(), EditXmbError> {
+        check_string(name)?;
+        if entry_index >= self.entries.len() {
+            return Err(EditXmbError::InvalidEntry(entry_index));
+        }
+
+        let collation = self.edit_collation();
+        self.entries[entry_index].name_offset = self.find_or_add_name(name, collation);
+        Ok(())
+    }
+
+    /// Add an attribute after the existing attributes for the entry at `entry_index`.
+    ///
+    /// The new attribute is inserted into [attributes](#structfield.attributes)
+    /// after the attributes of the entry or the previous entries,
+    /// so the start index for the attributes of later entries increases by 1.
+    /// Adding the `"id"` attribute also inserts a row for the entry in [mapped_entries](#structfield.mapped_entries).
+    pub fn add_attribute(
+        &mut self,
+        entry_index: usize,
+        name: &str,
+        value: &str,
+    ) -> Result<(), EditXmbError> {
+        check_string(name)?;
+        check_string(value)?;
+        let has_attribute = self.attribute_indices(entry_index)?.any(|i| {
+            read_string_bytes(&self.string_names.data, self.attributes[i].name_offset)
+                == Some(name.as_bytes())
+        });
+        if has_attribute {
+            return Err(EditXmbError::DuplicateAttribute {
+                entry: entry_index,
+                name: name.to_string(),
+            });
+        }
+        if self.attributes.len() >= i16::MAX as usize {
+            return Err(EditXmbError::TooManyAttributes);
+        }
+
+        let collation = self.edit_collation();
+        let attribute = Attribute {
+            name_offset: self.find_or_add_name(name, collation),
+            value_offset: self.find_or_add_value(value),
+        };
+
+        let insert_index = self.attribute_insert_index(entry_index);
+        let value_offset = attribute.value_offset;
+        self.attributes.insert(insert_index, attribute);
+        for (i, entry) in self.entries.iter_mut().enumerate() {
+            if i == entry_index {
+                if entry.attribute_count == 0 {
+                    entry.attribute_start_index = insert_index as i16;
+                }
+                entry.attribute_count += 1;
+            } else if entry.attribute_count > 0
+                && entry.attribute_start_index >= insert_index as i16
+            {
+                entry.attribute_start_index += 1;
+            }
+        }
+        self.attribute_count = self.attributes.len() as u32;
+
+        if name == ID_ATTRIBUTE {
+            self.update_mapped_entry(entry_index, value_offset, collation);
+        }
+        Ok(())
+    }
+
+    fn attribute_indices(
+        &self,
+        entry_index: usize,
+    ) -> Result<impl Iterator<Item = usize> + '_, EditXmbError> {
+        let entry = self
+            .entries
+            .get(entry_index)
+            .ok_or(EditXmbError::InvalidEntry(entry_index))?;
+        let start = usize::try_from(entry.attribute_start_index).unwrap_or_default();
+        let count = entry.attribute_count as usize;
+        Ok((start..start + count).filter(|i| *i < self.attributes.len()))
+    }
+
+    fn attribute_insert_index(&self, entry_index: usize) -> usize {
+        let entry = &self.entries[entry_index];
+        match usize::try_from(entry.attribute_start_index) {
+            Ok(start) if entry.attribute_count > 0 => {
+                (start + entry.attribute_count as usize).min(self.attributes.len())
+            }
+            // Insert after the attributes of the closest previous entry with attributes.
+            _ => self.entries[..entry_index]
+                .iter()
+                .rev()
+                .find(|e| e.attribute_count > 0 && e.attribute_start_index >= 0)
+                .map(|e| e.attribute_start_index as usize + e.attribute_count as usize)
+                .unwrap_or_default()
+                .min(self.attributes.len()),
+        }
+    }
+
+    fn edit_collation(&self) -> Collation {
+        self.detect_collations()
+            .ok()
+            .and_then(|c| c.first().copied())
+            .unwrap_or_default()
+    }
+
+    fn find_or_add_name(&mut self, name: &str, collation: Collation) -> u32 {
+        let data = &self.string_names.data;
+        if let Some(offset) = self
+            .string_offsets
+            .iter()
+            .find(|o| read_string_bytes(data, **o) == Some(name.as_bytes()))
+        {
+            return *offset;
+        }
+
+        // The names buffer is read by counting strings, so it has no padding.
+        let offset = data.len() as u32;
+        self.string_names.data.extend_from_slice(name.as_bytes());
+        self.string_names.data.push(0);
+
+        let data = &self.string_names.data;
+        let index = self.string_offsets.partition_point(|o| {
+            collation.compare(
+                read_string_bytes(data, *o).unwrap_or_default(),
+                name.as_bytes(),
+            ) != std::cmp::Ordering::Greater
+        });
+        self.string_offsets.insert(index, offset);
+        self.string_count = self.string_offsets.len() as u32;
+        offset
+    }
+
+    fn find_or_add_value(&mut self, value: &str) -> u32 {
+        let data = &self.string_values.data;
+        let referenced_offsets = || {
+            self.attributes
+                .iter()
+                .map(|a| a.value_offset)
+                .chain(self.mapped_entries.iter().map(|e| e.value_offset))
+        };
+        if let Some(offset) =
+            referenced_offsets().find(|o| read_string_bytes(data, *o) == Some(value.as_bytes()))
+        {
+            return offset;
+        }
+
+        // The values buffer includes the padding at the end of the file.
+        // Add the value after the last string to avoid keeping the old padding.
+        let content_end = data
+            .iter()
+            .rposition(|b| *b != 0)
+            .map(|i| i + 2)
+            .unwrap_or_default();
+        let referenced_end = referenced_offsets()
+            .filter_map(|o| read_string_bytes(data, o).map(|s| o as usize + s.len() + 1))
+            .max()
+            .unwrap_or_default();
+        let end = content_end.max(referenced_end).min(data.len());
+
+        self.string_values.data.truncate(end);
+        self.string_values.data.extend_from_slice(value.as_bytes());
+        self.string_values.data.push(0);
+        end as u32
+    }
+
+    // Only move the row for this entry to keep the order and duplicate ids of the other rows.
+    fn update_mapped_entry(&mut self, entry_index: usize, value_offset: u32, collation: Collation) {
+        self.mapped_entries
+            .retain(|e| e.entry_index as usize != entry_index);
+
+        let data = &self.string_values.data;
+        let value = read_string_bytes(data, value_offset).unwrap_or_default();
+        let index = self.mapped_entries.partition_point(|e| {
+            collation.compare(
+                read_string_bytes(data, e.value_offset).unwrap_or_default(),
+                value,
+            ) != std::cmp::Ordering::Greater
+        });
+        self.mapped_entries.insert(
+            index,
+            MappedEntry {
+                value_offset,
+                entry_index: entry_index as u32,
+            },
+        );
+        self.mapped_entry_count = self.mapped_entries.len() as u32;
+    }
+}
+
+fn check_string(s: &str) -> Result<(), EditXmbError> {
+    if s.contains('\0') {
+        Err(EditXmbError::NullByte(s.to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{XmbFile, XmbFileEntry};
+
+    fn mapped_ids(xmb: &Xmb) -> Vec<(String, u32)> {
+        xmb.mapped_entries
+            .iter()
+            .map(|e| (xmb.read_value(e.value_offset).unwrap(), e.entry_index))
+            .collect()
+    }
+
+    fn id_xmb() -> Xmb {
+        Xmb::from(&XmbFile {
+            entries: vec![XmbFileEntry::new("a")
+                .child(XmbFileEntry::new("b").attr("id", "x"))
+                .child(XmbFileEntry::new("c").attr("id", "x"))
+                .child(XmbFileEntry::new("d").attr("id", "y"))
+                .child(XmbFileEntry::new("e"))],
+        })
+    }
+
+    #[test]
+    fn set_id_duplicate_ids() {
+        let mut xmb = id_xmb();
+        // Use the first entry for duplicate ids unlike the builder.
+        xmb.mapped_entries[0].entry_index = 1;
+        assert_eq!(vec![("x".into(), 1), ("y".into(), 3)], mapped_ids(&xmb));
+
+        xmb.set_attribute_value(3, "id", "w").unwrap();
+        assert_eq!(vec![("w".into(), 3), ("x".into(), 1)], mapped_ids(&xmb));
+        assert_eq!(2, xmb.mapped_entry_count);
+
+        // Entries without a row get a new row after equal ids.
+        xmb.set_attribute_value(2, "id", "w").unwrap();
+        assert_eq!(
+            vec![("w".into(), 3), ("w".into(), 2), ("x".into(), 1)],
+            mapped_ids(&xmb)
+        );
+        assert_eq!(3, xmb.mapped_entry_count);
+    }
+
+    #[test]
+    fn add_id_duplicate_ids() {
+        let mut xmb = id_xmb();
+        xmb.mapped_entries[0].entry_index = 1;
+
+        xmb.add_attribute(4, "id", "x").unwrap();
+        assert_eq!(
+            vec![("x".into(), 1), ("x".into(), 4), ("y".into(), 3)],
+            mapped_ids(&xmb)
+        );
+        assert_eq!(3, xmb.mapped_entry_count);
+    }
+
+    #[test]
+    fn edit_errors() {
+        let mut xmb = id_xmb();
+        assert!(matches!(
+            xmb.set_attribute_value(5, "id", "z"),
+            Err(EditXmbError::InvalidEntry(5))
+        ));
+        assert!(matches!(
+            xmb.set_attribute_value(4, "id", "z"),
+            Err(EditXmbError::MissingAttribute { entry: 4, .. })
+        ));
+        assert!(matches!(
+            xmb.add_attribute(1, "id", "z"),
+            Err(EditXmbError::DuplicateAttribute { entry: 1, .. })
+        ));
+        assert!(matches!(
+            xmb.rename_entry(0, "a\0b"),
+            Err(EditXmbError::NullByte(_))
+        ));
+        assert!(matches!(
+            xmb.rename_entry(5, "f"),
+            Err(EditXmbError::InvalidEntry(5))
+        ));
+        assert!(xmb.repair().is_empty());
+    }
+
+    #[test]
+    fn add_value_after_padding() {
+        let mut xmb = Xmb::from(&XmbFile {
+            entries: vec![XmbFileEntry::new("a").attr("b", "c")],
+        });
+        // Files are padded to a multiple of 4 bytes.
+        xmb.string_values.data.extend_from_slice(&[0, 0, 0]);
+
+        xmb.add_attribute(0, "d", "e").unwrap();
+        assert_eq!(b"c\0e\0", xmb.string_values.data.as_slice());
+        assert_eq!(
+            Some("e".into()),
+            xmb.read_value(xmb.attributes[1].value_offset)
+        );
+    }
+
+    #[test]
+    fn edit_empty_xmb() {
+        let mut xmb = Xmb::from(&XmbFile {
+            entries: Vec::new(),
+        });
+        assert!(matches!(
+            xmb.add_attribute(0, "id", "x"),
+            Err(EditXmbError::InvalidEntry(0))
+        ));
+    }
+}
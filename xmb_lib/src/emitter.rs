@@ -0,0 +1,311 @@
+//! Write XML text directly from entries without creating an [Element].
+use crate::events::XmbEvent;
+use crate::meta::XmbMeta;
+use crate::xmb::Xmb;
+use crate::{
+    namespace_declaration_prefix, CreateXmlError, EmptyElementStyle, WriteXmlError, XmbFile,
+    XmbFileEntry, XmlOptions,
+};
+use indexmap::IndexMap;
+use std::borrow::Cow;
+use std::io::Write;
+use xml::attribute::Attribute;
+use xml::common::XmlVersion;
+use xml::name::Name;
+use xml::namespace::{Namespace, NamespaceStack, NS_NO_PREFIX};
+use xml::writer::{EmitterConfig, EventWriter, XmlEvent};
+use xmltree::{Element, XMLNode};
+
+impl Xmb {
+    /// Write the entries as an XML document to `writer`.
+    ///
+    /// The output is the same as [XmbFile::write_xml] for the converted [XmbFile].
+    /// Entries are written as they are read without creating an intermediate tree.
+    ///
+    /// Returns [ReadXmbError::InvalidEntry](crate::ReadXmbError::InvalidEntry) if a name, attribute, or value can't be read.
+    /// Some of the document may already be written in this case.
+    pub fn write_xml<W: Write>(
+        &self,
+        writer: W,
+        options: &XmlOptions,
+    ) -> Result<(), WriteXmlError> {
+        check_root_count(self.entries.iter().filter(|e| e.parent_index == -1).count())?;
+
+        let mut writer = EntryWriter::new(writer, options)?;
+        for event in self.events() {
+            match event? {
+                XmbEvent::StartEntry {
+                    name, attributes, ..
+                } => {
+                    // Duplicate attributes use the last value like XmbFile.
+                    let attributes: IndexMap<_, _> = attributes.into_iter().collect();
+                    writer.start_entry(&name, attributes.iter().map(|(k, v)| (&**k, &**v)))?;
+                }
+                XmbEvent::EndEntry => writer.end_entry()?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl XmbFile {
+    /// Write the entries as an XML document to `writer`.
+    ///
+    /// The output is the same as writing the [Element] from [to_xml](#method.to_xml)
+    /// with the equivalent [EmitterConfig] without creating an intermediate tree.
+    pub fn write_xml<W: Write>(
+        &self,
+        writer: W,
+        options: &XmlOptions,
+    ) -> Result<(), WriteXmlError> {
+        check_root_count(self.entries.len())?;
+
+        let mut writer = EntryWriter::new(writer, options)?;
+        write_entry_recursive(&mut writer, &self.entries[0])?;
+        Ok(())
+    }
+
+    /// Write the entries as an XML document like [write_xml](#method.write_xml) and return the text.
+    pub fn to_xml_string(&self, options: &XmlOptions) -> Result<String, WriteXmlError> {
+        let mut text = Vec::new();
+        self.write_xml(&mut text, options)?;
+        // The writer only writes valid UTF-8.
+        Ok(String::from_utf8(text).unwrap())
+    }
+
+    /// Write the entries as an XML document like [write_xml](#method.write_xml)
+    /// with the comments and processing instructions from `meta`.
+    /// See [to_xml_with_meta](#method.to_xml_with_meta) for details.
+    pub fn write_xml_with_meta<W: Write>(
+        &self,
+        writer: W,
+        meta: &XmbMeta,
+        options: &XmlOptions,
+    ) -> Result<(), WriteXmlError> {
+        let element = self.to_xml_with_meta(meta)?;
+
+        let mut writer = EntryWriter::new(writer, options)?;
+        write_element_recursive(&mut writer.writer, &element, options.empty_elements)?;
+        Ok(())
+    }
+}
+
+impl XmlOptions {
+    fn emitter_config(&self) -> EmitterConfig {
+        let config = EmitterConfig::new()
+            .perform_indent(self.indent.is_some())
+            .write_document_declaration(false);
+        let config = match &self.indent {
+            Some(indent) => config.indent_string(indent.clone()),
+            None => config,
+        };
+        match self.empty_elements {
+            EmptyElementStyle::SelfClosing => config.pad_self_closing(false),
+            EmptyElementStyle::PaddedSelfClosing => config.pad_self_closing(true),
+            EmptyElementStyle::StartEnd => config.normalize_empty_elements(false),
+        }
+    }
+}
+
+fn check_root_count(count: usize) -> Result<(), CreateXmlError> {
+    match count {
+        0 => Err(CreateXmlError::MissingRootElement),
+        1 => Ok(()),
+        _ => Err(CreateXmlError::MultipleRootElements),
+    }
+}
+
+fn write_entry_recursive<W: Write>(
+    writer: &mut EntryWriter<W>,
+    entry: &XmbFileEntry,
+) -> Result<(), xml::writer::Error> {
+    writer.start_entry(
+        &entry.name,
+        entry
+            .attributes
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str())),
+    )?;
+    for child in &entry.children {
+        write_entry_recursive(writer, child)?;
+    }
+    writer.end_entry()
+}
+
+// Write entries with the same namespace handling as XmbFile::to_xml.
+struct EntryWriter<W: Write> {
+    writer: EventWriter<W>,
+    // Most documents don't declare namespaces,
+    // so elements refer to the scope of the nearest declaring ancestor by index.
+    scopes: Vec<Namespace>,
+    open_scopes: Vec<usize>,
+    empty_elements: EmptyElementStyle,
+    // The last started entry has no children yet.
+    is_empty: bool,
+}
+
+impl<W: Write> EntryWriter<W> {
+    fn new(writer: W, options: &XmlOptions) -> Result<Self, xml::writer::Error> {
+        let mut writer = EventWriter::new_with_config(writer, options.emitter_config());
+        if options.declaration {
+            writer.write(XmlEvent::StartDocument {
+                version: XmlVersion::Version10,
+                encoding: Some(options.encoding.name()),
+                standalone: None,
+            })?;
+        }
+
+        Ok(Self {
+            writer,
+            scopes: vec![NamespaceStack::default().squash()],
+            open_scopes: Vec::new(),
+            empty_elements: options.empty_elements,
+            is_empty: false,
+        })
+    }
+
+    fn start_entry<'b, I>(&mut self, name: &str, attributes: I) -> Result<(), xml::writer::Error>
+    where
+        I: IntoIterator<Item = (&'b str, &'b str)>,
+    {
+        let parent_scope = self.open_scopes.last().copied().unwrap_or_default();
+
+        // Namespace declarations are stored as attributes like xmlns:prefix="uri".
+        let mut scope = parent_scope;
+        let mut element_attributes = Vec::new();
+        for (k, v) in attributes {
+            match namespace_declaration_prefix(k) {
+                Some(prefix) => {
+                    if scope == parent_scope {
+                        self.scopes.push(self.scopes[parent_scope].clone());
+                        scope = self.scopes.len() - 1;
+                    }
+                    self.scopes[scope].force_put(prefix, v);
+                }
+                None => element_attributes.push(Attribute::new(Name::local(k), v)),
+            }
+        }
+        self.open_scopes.push(scope);
+
+        let namespaces = &self.scopes[scope];
+        let (prefix, local_name) = match name.split_once(':') {
+            Some((prefix, name)) => (Some(prefix), name),
+            None => (None, name),
+        };
+        let namespace = namespaces
+            .get(prefix.unwrap_or(NS_NO_PREFIX))
+            .filter(|uri| !uri.is_empty());
+
+        let empty = Namespace::empty();
+        self.writer.write(XmlEvent::StartElement {
+            name: Name {
+                local_name,
+                namespace,
+                prefix,
+            },
+            attributes: Cow::Owned(element_attributes),
+            namespace: if namespaces.is_essentially_empty() {
+                Cow::Borrowed(&empty)
+            } else {
+                Cow::Borrowed(namespaces)
+            },
+        })?;
+        self.is_empty = true;
+        Ok(())
+    }
+
+    fn end_entry(&mut self) -> Result<(), xml::writer::Error> {
+        // Scopes are only shared with descendants, so remove the scope if this element declared it.
+        if let Some(scope) = self.open_scopes.pop() {
+            if scope != self.open_scopes.last().copied().unwrap_or_default() {
+                self.scopes.truncate(scope);
+            }
+        }
+        if self.is_empty {
+            write_empty_content(&mut self.writer, self.empty_elements)?;
+        }
+        self.is_empty = false;
+        self.writer.write(XmlEvent::EndElement { name: None })
+    }
+}
+
+// Write the element like Element::write_with_config.
+fn write_element_recursive<W: Write>(
+    writer: &mut EventWriter<W>,
+    element: &Element,
+    empty_elements: EmptyElementStyle,
+) -> Result<(), xml::writer::Error> {
+    let name = Name {
+        local_name: &element.name,
+        namespace: element.namespace.as_deref(),
+        prefix: element.prefix.as_deref(),
+    };
+    let attributes = element
+        .attributes
+        .iter()
+        .map(|(k, v)| Attribute::new(Name::local(k), v))
+        .collect();
+
+    let empty = Namespace::empty();
+    writer.write(XmlEvent::StartElement {
+        name,
+        attributes: Cow::Owned(attributes),
+        namespace: Cow::Borrowed(element.namespaces.as_ref().unwrap_or(&empty)),
+    })?;
+
+    for child in &element.children {
+        match child {
+            XMLNode::Element(child) => write_element_recursive(writer, child, empty_elements)?,
+            XMLNode::Comment(comment) => writer.write(XmlEvent::Comment(comment))?,
+            XMLNode::CData(text) => writer.write(XmlEvent::CData(text))?,
+            XMLNode::Text(text) => writer.write(XmlEvent::Characters(text))?,
+            XMLNode::ProcessingInstruction(name, data) => {
+                writer.write(XmlEvent::ProcessingInstruction {
+                    name,
+                    data: data.as_deref(),
+                })?
+            }
+        }
+    }
+    if element.children.is_empty() {
+        write_empty_content(writer, empty_elements)?;
+    }
+
+    writer.write(XmlEvent::EndElement { name: None })
+}
+
+fn write_empty_content<W: Write>(
+    writer: &mut EventWriter<W>,
+    empty_elements: EmptyElementStyle,
+) -> Result<(), xml::writer::Error> {
+    // Empty text keeps the end tag on the same line like <a></a> when indenting.
+    if empty_elements == EmptyElementStyle::StartEnd {
+        writer.write(XmlEvent::Characters(""))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_writer_removes_closed_scopes() {
+        let mut writer = EntryWriter::new(Vec::new(), &XmlOptions::default()).unwrap();
+        writer
+            .start_entry("root", [("xmlns:a", "http://example.com/a")])
+            .unwrap();
+        for _ in 0..3 {
+            writer
+                .start_entry("a:b", [("xmlns:c", "http://example.com/c")])
+                .unwrap();
+            writer.start_entry("c:d", []).unwrap();
+            writer.end_entry().unwrap();
+            writer.end_entry().unwrap();
+            assert_eq!(2, writer.scopes.len());
+        }
+        writer.end_entry().unwrap();
+        assert_eq!(1, writer.scopes.len());
+        assert!(writer.open_scopes.is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+//! Pull-style iteration over the entries of an [Xmb] in document order.
+//!
+//! Events are read directly from the entry table and string buffers.
+//! This is useful for scanning or transforming entries without creating an [XmbFile](crate::XmbFile).
+use crate::xmb::Xmb;
+use crate::ReadXmbError;
+use std::borrow::Cow;
+
+/// An event for an entry in document order like an XML pull parser.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum XmbEvent<'a> {
+    /// The start of an entry with the given index in [entries](crate::xmb::Xmb::entries).
+    /// Any children appear before the matching [XmbEvent::EndEntry].
+    StartEntry {
+        index: usize,
+        name: Cow<'a, str>,
+        attributes: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+    },
+    /// The end of the most recently started entry.
+    EndEntry,
+}
+
+/// An iterator over the [XmbEvent] for each entry created by [Xmb::events].
+///
+/// Stops after the first error.
+pub struct XmbEvents<'a> {
+    xmb: &'a Xmb,
+    children: Vec<Vec<usize>>,
+    steps: Vec<Step>,
+}
+
+enum Step {
+    Start(usize),
+    End,
+}
+
+impl Xmb {
+    /// Iterate over the start and end of each entry in document order.
+    /// Entries are visited like the elements of [XmbFile::to_xml](crate::XmbFile::to_xml),
+    /// so each root entry and its children appear before the next root entry.
+    /// # Examples
+    /// Count entries by name without converting to [XmbFile](crate::XmbFile).
+    /**
+    ```rust no_run
+    use xmb_lib::events::XmbEvent;
+    use xmb_lib::xmb::Xmb;
+
+    # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let xmb = Xmb::from_file("model.xmb")?;
+    let mut draw_count = 0;
+    for event in xmb.events() {
+        if let XmbEvent::StartEntry { name, .. } = event? {
+            if name == "draw" {
+                draw_count += 1;
+            }
+        }
+    }
+    # Ok(())
+    # }
+    ```
+     */
+    pub fn events(&self) -> XmbEvents<'_> {
+        let children = entry_children(self);
+        let steps = children[self.entries.len()]
+            .iter()
+            .rev()
+            .map(|i| Step::Start(*i))
+            .collect();
+
+        XmbEvents {
+            xmb: self,
+            children,
+            steps,
+        }
+    }
+}
+
+impl<'a> Iterator for XmbEvents<'a> {
+    type Item = Result<XmbEvent<'a>, ReadXmbError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.steps.pop()? {
+            Step::Start(index) => match start_entry(self.xmb, index) {
+                Some(event) => {
+                    // The stack is last in first out, so the end is visited after all the children.
+                    self.steps.push(Step::End);
+                    self.steps
+                        .extend(self.children[index].iter().rev().map(|c| Step::Start(*c)));
+                    Some(Ok(event))
+                }
+                None => {
+                    self.steps.clear();
+                    Some(Err(ReadXmbError::InvalidEntry(index)))
+                }
+            },
+            Step::End => Some(Ok(XmbEvent::EndEntry)),
+        }
+    }
+}
+
+impl std::iter::FusedIterator for XmbEvents<'_> {}
+
+fn start_entry(xmb: &Xmb, index: usize) -> Option<XmbEvent<'_>> {
+    let entry = &xmb.entries[index];
+    let name = read_string(&xmb.string_names.data, entry.name_offset)?;
+
+    let attributes = (0..entry.attribute_count)
+        .map(|i| {
+            // TODO: Don't perform unchecked arithmetic and indexing with signed numbers.
+            let attribute_index = (entry.attribute_start_index as u16).wrapping_add(i) as usize;
+            let attribute = xmb.attributes.get(attribute_index)?;
+            let key = read_string(&xmb.string_names.data, attribute.name_offset)?;
+            let value = read_string(&xmb.string_values.data, attribute.value_offset)?;
+            Some((key, value))
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(XmbEvent::StartEntry {
+        index,
+        name,
+        attributes,
+    })
+}
+
+// Borrow the null terminated string at offset like Xmb::read_name or Xmb::read_value.
+pub(crate) fn read_string(data: &[u8], offset: u32) -> Option<Cow<'_, str>> {
+    read_string_bytes(data, offset).map(String::from_utf8_lossy)
+}
+
+// The bytes of the null terminated string at offset without the null terminator.
+pub(crate) fn read_string_bytes(data: &[u8], offset: u32) -> Option<&[u8]> {
+    let bytes = data.get(offset as usize..)?;
+    let end = bytes.iter().position(|b| *b == 0)?;
+    Some(&bytes[..end])
+}
+
+// The child indices for each entry with the root indices at the end.
+pub(crate) fn entry_children(xmb: &Xmb) -> Vec<Vec<usize>> {
+    let count = xmb.entries.len();
+    let mut children = vec![Vec::new(); count + 1];
+    for (i, entry) in xmb.entries.iter().enumerate() {
+        if entry.parent_index == -1 {
+            children[count].push(i);
+        } else if let Ok(parent) = usize::try_from(entry.parent_index) {
+            if parent < count {
+                children[parent].push(i);
+            }
+        }
+    }
+    children
+}
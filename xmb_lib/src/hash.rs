@@ -0,0 +1,225 @@
+//! Hashing the names, attributes, and structure of entries.
+//!
+//! The hash doesn't depend on the layout of the string buffers, the order of the lookup tables,
+//! or the byte order of the file. An [XmbFile] and the [Xmb] it converts to and from have the same hash.
+//!
+//! The hash is the 64-bit FNV-1a hash of the following encoding for each entry in document order.
+//! Lengths are in bytes and stored as little endian `u64` values.
+//! Attributes are sorted by the bytes of their names, so the hash doesn't depend on the order of attributes
+//! and files that compare equal with [PartialEq] have the same hash.
+//! Duplicate attributes in an [Xmb] are combined like when converting to [XmbFile].
+//!
+//! | Item | Encoding |
+//! | --- | --- |
+//! | Start of entry | `0x01`, name length, name bytes, attribute count |
+//! | Attribute | name length, name bytes, value length, value bytes |
+//! | End of entry | `0x02` |
+//!
+//! The encoding and hash function won't change without a new major version.
+use crate::events::XmbEvent;
+use crate::xmb::Xmb;
+use crate::{ReadXmbError, XmbFile, XmbFileEntry};
+use indexmap::IndexMap;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+const START_ENTRY: u8 = 1;
+const END_ENTRY: u8 = 2;
+
+impl XmbFile {
+    /// Calculate a hash of the entries as described in the [module docs](crate::hash).
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = ContentHasher::new();
+        for entry in &self.entries {
+            hash_entry_recursive(&mut hasher, entry);
+        }
+        hasher.finish()
+    }
+}
+
+impl Xmb {
+    /// Calculate a hash of the entries as described in the [module docs](crate::hash).
+    /// This is the same as the hash for the converted [XmbFile] without allocating all entries.
+    pub fn content_hash(&self) -> Result<u64, ReadXmbError> {
+        let mut hasher = ContentHasher::new();
+        for event in self.events() {
+            match event? {
+                XmbEvent::StartEntry {
+                    name, attributes, ..
+                } => {
+                    let attributes: IndexMap<_, _> = attributes.into_iter().collect();
+                    hasher.start_entry(&name, attributes.iter().map(|(k, v)| (&**k, &**v)));
+                }
+                XmbEvent::EndEntry => hasher.end_entry(),
+            }
+        }
+        Ok(hasher.finish())
+    }
+}
+
+fn hash_entry_recursive(hasher: &mut ContentHasher, entry: &XmbFileEntry) {
+    hasher.start_entry(
+        &entry.name,
+        entry
+            .attributes
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str())),
+    );
+    for child in &entry.children {
+        hash_entry_recursive(hasher, child);
+    }
+    hasher.end_entry();
+}
+
+struct ContentHasher {
+    hash: u64,
+}
+
+impl ContentHasher {
+    fn new() -> Self {
+        Self {
+            hash: FNV_OFFSET_BASIS,
+        }
+    }
+
+    fn start_entry<'a, I>(&mut self, name: &str, attributes: I)
+    where
+        I: Iterator<Item = (&'a str, &'a str)>,
+    {
+        // Names are unique, so sorting by name is enough for a consistent order.
+        let mut attributes: Vec<_> = attributes.collect();
+        attributes.sort_unstable_by_key(|(k, _)| k.as_bytes());
+
+        self.write(&[START_ENTRY]);
+        self.write_str(name);
+        self.write_len(attributes.len());
+        for (k, v) in attributes {
+            self.write_str(k);
+            self.write_str(v);
+        }
+    }
+
+    fn end_entry(&mut self) {
+        self.write(&[END_ENTRY]);
+    }
+
+    fn write_str(&mut self, s: &str) {
+        self.write_len(s.len());
+        self.write(s.as_bytes());
+    }
+
+    fn write_len(&mut self, len: usize) {
+        self.write(&(len as u64).to_le_bytes());
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.hash ^= *b as u64;
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::{BuildOptions, StringLayout};
+
+    #[test]
+    fn xmb_file_content_hash() {
+        let xmb_file = XmbFile {
+            entries: vec![XmbFileEntry::new("a")
+                .attr("id", "b")
+                .child(XmbFileEntry::new("b").attr("x", "1"))
+                .child(XmbFileEntry::new("c"))],
+        };
+        let hash = xmb_file.content_hash();
+
+        // The hash should not depend on the string layout.
+        for string_layout in [StringLayout::Sorted, StringLayout::SuffixMerged] {
+            let xmb = xmb_file.to_xmb(&BuildOptions {
+                string_layout,
+                ..Default::default()
+            });
+            assert_eq!(hash, xmb.content_hash().unwrap());
+        }
+
+        // Check for ambiguity between names, attributes, and structure.
+        let others = [
+            XmbFile {
+                entries: vec![XmbFileEntry::new("a").attr("id", "b").child(
+                    XmbFileEntry::new("b")
+                        .attr("x", "1")
+                        .child(XmbFileEntry::new("c")),
+                )],
+            },
+            XmbFile {
+                entries: vec![XmbFileEntry::new("a")
+                    .attr("id", "b")
+                    .child(XmbFileEntry::new("b").attr("x1", ""))
+                    .child(XmbFileEntry::new("c"))],
+            },
+            XmbFile {
+                entries: vec![XmbFileEntry::new("a")
+                    .attr("id", "b")
+                    .child(XmbFileEntry::new("b").attr("x", "1"))
+                    .child(XmbFileEntry::new("c").attr("", ""))],
+            },
+            XmbFile {
+                entries: vec![XmbFileEntry::new("a")
+                    .attr("x", "1")
+                    .attr("id", "b")
+                    .child(XmbFileEntry::new("b").attr("x", "1"))
+                    .child(XmbFileEntry::new("c"))],
+            },
+        ];
+        for other in others {
+            assert_ne!(hash, other.content_hash());
+        }
+
+        // Attribute order should not affect the hash like PartialEq.
+        let reordered = XmbFile {
+            entries: vec![XmbFileEntry::new("a")
+                .attr("x", "1")
+                .attr("id", "b")
+                .child(XmbFileEntry::new("c"))],
+        };
+        let original = XmbFile {
+            entries: vec![XmbFileEntry::new("a")
+                .attr("id", "b")
+                .attr("x", "1")
+                .child(XmbFileEntry::new("c"))],
+        };
+        assert_eq!(original, reordered);
+        assert_eq!(original.content_hash(), reordered.content_hash());
+        assert_eq!(
+            original.content_hash(),
+            Xmb::from(&reordered).content_hash().unwrap()
+        );
+
+        // Hashes should be stable across versions.
+        let empty = XmbFile {
+            entries: Vec::new(),
+        };
+        assert_eq!(0xcbf29ce484222325, empty.content_hash());
+        assert_eq!(
+            0x469e05c5cd98b172,
+            XmbFile {
+                entries: vec![XmbFileEntry::new("a")]
+            }
+            .content_hash()
+        );
+        assert_eq!(
+            0x0847948a026e3b37,
+            XmbFile {
+                entries: vec![XmbFileEntry::new("a").attr("x", "1").attr("id", "b")]
+            }
+            .content_hash()
+        );
+    }
+}
@@ -0,0 +1,315 @@
+//! Lookups by id, name, and references for the entries in an [XmbFile].
+//!
+//! Entries can also be iterated in document order with [XmbFile::iter_document_order]
+//! or in the order of the binary entries with [XmbFile::iter_flattened].
+//!
+//! [Xmb] files store a sorted [mapped_entries](crate::xmb::Xmb::mapped_entries) table for finding entries by id.
+//! [XmbIndex] provides the same lookup for an [XmbFile] along with lookups that aren't stored in the file.
+use crate::{XmbFile, XmbFileEntry};
+use std::collections::HashMap;
+
+#[cfg(doc)]
+use crate::xmb::Xmb;
+
+/// The attribute used for the [mapped_entries](crate::xmb::Xmb::mapped_entries) lookup.
+pub const ID_ATTRIBUTE: &str = "id";
+
+/// An entry in an [XmbFile] and its location in the tree.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct IndexedEntry<'a> {
+    /// The index into [entries](crate::XmbFile::entries)
+    /// followed by the index into [children](crate::XmbFileEntry::children) for each level.
+    /// See [XmbFile::entry_at_path].
+    pub path: Vec<usize>,
+    /// The number of ancestors or 0 for entries in [entries](crate::XmbFile::entries).
+    pub depth: usize,
+    /// The index in [entries](crate::xmb::Xmb::entries) when converted to [Xmb].
+    pub index: usize,
+    pub entry: &'a XmbFileEntry,
+}
+
+/// An iterator over entries in the order of [entries](crate::xmb::Xmb::entries) created by [XmbFile::iter_flattened].
+#[derive(Debug)]
+pub struct FlattenedEntries<'a> {
+    // Sibling entries and their parent's path that haven't been visited yet.
+    groups: Vec<(&'a [XmbFileEntry], Vec<usize>)>,
+    current: Option<(&'a [XmbFileEntry], Vec<usize>, usize)>,
+    index: usize,
+}
+
+/// An iterator over entries in the order they appear in XML created by [XmbFile::iter_document_order].
+#[derive(Debug)]
+pub struct DocumentEntries<'a> {
+    // The siblings for each level of the current path,
+    // the index of the next sibling, and the flattened index of the first sibling.
+    stack: Vec<(&'a [XmbFileEntry], usize, usize)>,
+    path: Vec<usize>,
+    // The flattened index of the first child for the next entry with children.
+    next_children_index: usize,
+    remaining: usize,
+}
+
+/// An attribute that has the id of another entry as its value.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Reference<'a> {
+    /// The entry containing the attribute.
+    pub entry: &'a IndexedEntry<'a>,
+    /// The name of the attribute with the id as its value.
+    pub attribute: &'a str,
+}
+
+/// Lookups for the entries of an [XmbFile] created by [XmbFile::index].
+///
+/// The index borrows the file, so it should be recreated after making changes.
+/// Entries are stored in the same order as [entries](crate::xmb::Xmb::entries) when converted to [Xmb].
+#[derive(Debug)]
+pub struct XmbIndex<'a> {
+    entries: Vec<IndexedEntry<'a>>,
+    entry_by_id: HashMap<&'a str, usize>,
+    entries_by_name: HashMap<&'a str, Vec<usize>>,
+    references_by_value: HashMap<&'a str, Vec<(usize, &'a str)>>,
+}
+
+impl<'a> XmbIndex<'a> {
+    /// Index all the entries in `xmb_file`.
+    pub fn new(xmb_file: &'a XmbFile) -> Self {
+        let entries: Vec<_> = xmb_file.iter_flattened().collect();
+
+        let mut entry_by_id = HashMap::new();
+        let mut entries_by_name: HashMap<_, Vec<_>> = HashMap::new();
+        let mut references_by_value: HashMap<_, Vec<_>> = HashMap::new();
+        for (i, e) in entries.iter().enumerate() {
+            entries_by_name
+                .entry(e.entry.name.as_str())
+                .or_default()
+                .push(i);
+
+            for (name, value) in &e.entry.attributes {
+                if name == ID_ATTRIBUTE {
+                    // Use the last entry for duplicate ids like when building an Xmb.
+                    entry_by_id.insert(value.as_str(), i);
+                } else {
+                    references_by_value
+                        .entry(value.as_str())
+                        .or_default()
+                        .push((i, name.as_str()));
+                }
+            }
+        }
+
+        Self {
+            entries,
+            entry_by_id,
+            entries_by_name,
+            references_by_value,
+        }
+    }
+
+    /// All entries in the same order as [entries](crate::xmb::Xmb::entries) when converted to [Xmb].
+    pub fn entries(&self) -> &[IndexedEntry<'a>] {
+        &self.entries
+    }
+
+    /// The entry with the given value for the [ID_ATTRIBUTE].
+    /// If multiple entries have the same id, this returns the same entry as the game
+    /// by using the last entry in the order of [entries](#method.entries).
+    pub fn get_by_id(&self, id: &str) -> Option<&IndexedEntry<'a>> {
+        self.entry_by_id.get(id).map(|i| &self.entries[*i])
+    }
+
+    /// All entries with the given name in the order of [entries](#method.entries).
+    pub fn entries_by_name<'b>(
+        &'b self,
+        name: &str,
+    ) -> impl Iterator<Item = &'b IndexedEntry<'a>> + 'b {
+        self.entries_by_name
+            .get(name)
+            .into_iter()
+            .flatten()
+            .map(|i| &self.entries[*i])
+    }
+
+    /// All attributes other than the [ID_ATTRIBUTE] with `id` as their value
+    /// in the order of [entries](#method.entries).
+    ///
+    /// References are found by value, so this also finds references to ids that don't exist.
+    pub fn references_to<'b>(&'b self, id: &str) -> impl Iterator<Item = Reference<'b>> + 'b {
+        self.references_by_value
+            .get(id)
+            .into_iter()
+            .flatten()
+            .map(|(i, attribute)| Reference {
+                entry: &self.entries[*i],
+                attribute,
+            })
+    }
+}
+
+impl XmbFile {
+    /// Create an index for finding entries by id, name, or references.
+    pub fn index(&self) -> XmbIndex<'_> {
+        XmbIndex::new(self)
+    }
+
+    /// Iterate over all entries in the same order as [entries](crate::xmb::Xmb::entries) when converted to [Xmb].
+    /// All siblings appear before any of their children.
+    pub fn iter_flattened(&self) -> FlattenedEntries<'_> {
+        FlattenedEntries {
+            groups: Vec::new(),
+            current: Some((&self.entries, Vec::new(), 0)),
+            index: 0,
+        }
+    }
+
+    /// Iterate over all entries in the order they appear in XML.
+    /// Each entry appears before its children.
+    pub fn iter_document_order(&self) -> DocumentEntries<'_> {
+        DocumentEntries {
+            stack: vec![(&self.entries, 0, 0)],
+            path: Vec::new(),
+            next_children_index: self.entries.len(),
+            remaining: count_entries(&self.entries),
+        }
+    }
+
+    /// The entry at `path` or `None` if any index is out of bounds.
+    /// See [IndexedEntry::path].
+    pub fn entry_at_path(&self, path: &[usize]) -> Option<&XmbFileEntry> {
+        let (first, rest) = path.split_first()?;
+        rest.iter()
+            .try_fold(self.entries.get(*first)?, |e, i| e.children.get(*i))
+    }
+
+    /// The entry at `path` or `None` if any index is out of bounds.
+    /// See [IndexedEntry::path].
+    pub fn entry_at_path_mut(&mut self, path: &[usize]) -> Option<&mut XmbFileEntry> {
+        let (first, rest) = path.split_first()?;
+        rest.iter()
+            .try_fold(self.entries.get_mut(*first)?, |e, i| e.children.get_mut(*i))
+    }
+}
+
+impl<'a> Iterator for FlattenedEntries<'a> {
+    type Item = IndexedEntry<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (siblings, parent_path, i) = self.current.as_mut()?;
+            if let Some(entry) = siblings.get(*i) {
+                let path = [parent_path.as_slice(), &[*i]].concat();
+                *i += 1;
+
+                let item = IndexedEntry {
+                    depth: parent_path.len(),
+                    path,
+                    index: self.index,
+                    entry,
+                };
+                self.index += 1;
+                return Some(item);
+            }
+
+            // Visit the children of each sibling in order after all siblings.
+            let (siblings, parent_path, _) = self.current.take()?;
+            self.groups
+                .extend(siblings.iter().enumerate().rev().map(|(i, entry)| {
+                    (
+                        entry.children.as_slice(),
+                        [parent_path.as_slice(), &[i]].concat(),
+                    )
+                }));
+            self.current = self.groups.pop().map(|(s, p)| (s, p, 0));
+        }
+    }
+}
+
+impl std::iter::FusedIterator for FlattenedEntries<'_> {}
+
+impl<'a> Iterator for DocumentEntries<'a> {
+    type Item = IndexedEntry<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (siblings, next, first_index) = self.stack.last_mut()?;
+            let i = *next;
+            let Some(entry) = siblings.get(i) else {
+                self.stack.pop();
+                continue;
+            };
+            *next += 1;
+            let index = *first_index + i;
+
+            let depth = self.stack.len() - 1;
+            self.path.truncate(depth);
+            self.path.push(i);
+
+            // The flattened order visits groups of children in the same order
+            // as their parents appear in the document.
+            // Each group starts after all the groups of the previous parents.
+            self.stack
+                .push((&entry.children, 0, self.next_children_index));
+            self.next_children_index += entry.children.len();
+
+            self.remaining -= 1;
+            return Some(IndexedEntry {
+                path: self.path.clone(),
+                depth,
+                index,
+                entry,
+            });
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for DocumentEntries<'_> {}
+
+impl std::iter::FusedIterator for DocumentEntries<'_> {}
+
+fn count_entries(entries: &[XmbFileEntry]) -> usize {
+    entries.iter().map(|e| 1 + count_entries(&e.children)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::xmb;
+
+    #[test]
+    fn document_order_empty() {
+        let xmb_file = xmb! {};
+        let mut entries = xmb_file.iter_document_order();
+        assert_eq!(0, entries.len());
+        assert_eq!(None, entries.next());
+        assert_eq!(None, entries.next());
+    }
+
+    #[test]
+    fn document_order_matches_sorted_paths() {
+        let xmb_file = xmb! {
+            a {
+                b {
+                    c { d; }
+                    e;
+                }
+                f;
+                g { h; i { j; } }
+            }
+            k;
+            l { m { n; } o; }
+        };
+
+        let mut expected: Vec<_> = xmb_file.iter_flattened().collect();
+        expected.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut entries = xmb_file.iter_document_order();
+        let mut actual = Vec::new();
+        while let Some(entry) = entries.next() {
+            actual.push(entry);
+            assert_eq!(expected.len() - actual.len(), entries.len());
+        }
+        assert_eq!(expected, actual);
+    }
+}
@@ -0,0 +1,343 @@
+//! Offsets and sizes of the sections in an XMB file.
+use crate::xmb::{NamesBuffer, Xmb};
+use crate::ReadXmbError;
+use binrw::{BinRead, BinReaderExt};
+use std::io::{Read, Seek, SeekFrom};
+use thiserror::Error;
+
+/// The size in bytes of the header including the magic, counts, offsets, and unknown values.
+pub const HEADER_SIZE: u32 = 64;
+
+pub(crate) const STRING_OFFSET_SIZE: u32 = 4;
+pub(crate) const ENTRY_SIZE: u32 = 16;
+pub(crate) const ATTRIBUTE_SIZE: u32 = 8;
+pub(crate) const MAPPED_ENTRY_SIZE: u32 = 8;
+pub(crate) const ALIGNMENT: u32 = 4;
+
+/// The location of a section relative to the start of the file.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Section {
+    pub offset: u32,
+    pub size: u32,
+}
+
+impl Section {
+    /// The offset of the first byte after the section.
+    pub fn end(&self) -> u32 {
+        self.offset + self.size
+    }
+}
+
+/// The sections of an XMB file in the order they appear in the file.
+/// Sizes do not include alignment padding.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct XmbLayout {
+    pub header: Section,
+    pub string_offsets: Section,
+    pub entries: Section,
+    pub attributes: Section,
+    pub mapped_entries: Section,
+    pub string_names: Section,
+    pub string_values: Section,
+    /// The total size of the file including padding at the end.
+    pub file_size: u32,
+}
+
+// The header without reading the data for each section.
+#[derive(BinRead)]
+#[br(magic(b"XMB "))]
+pub(crate) struct Header {
+    pub entry_count: u32,
+    pub attribute_count: u32,
+    pub string_count: u32,
+    pub mapped_entry_count: u32,
+    pub string_offsets_offset: u32,
+    pub entries_offset: u32,
+    pub attributes_offset: u32,
+    pub mapped_entries_offset: u32,
+    pub string_names_offset: u32,
+    pub string_values_offset: u32,
+    pub unks: [u32; 5],
+}
+
+/// A section that doesn't fit in the 32-bit offsets and sizes used by XMB files.
+#[derive(Debug, Error)]
+#[error("the {0} section does not fit in a 32-bit offset")]
+pub struct LayoutOverflowError(pub &'static str);
+
+impl Xmb {
+    /// Calculate the sections for the file produced by [write](#method.write) without writing any data.
+    ///
+    /// The counts like [entry_count](#structfield.entry_count) are assumed to match the lengths of the data.
+    /// Empty sections have a size of 0 and the offset of the next section.
+    pub fn layout(&self) -> Result<XmbLayout, LayoutOverflowError> {
+        let header = Section {
+            offset: 0,
+            size: HEADER_SIZE,
+        };
+        let string_offsets = next_section(
+            "string_offsets",
+            &header,
+            section_size(
+                "string_offsets",
+                self.string_offsets.len(),
+                STRING_OFFSET_SIZE,
+            )?,
+            1,
+        )?;
+        let entries = next_section(
+            "entries",
+            &string_offsets,
+            section_size("entries", self.entries.len(), ENTRY_SIZE)?,
+            1,
+        )?;
+        let attributes = next_section(
+            "attributes",
+            &entries,
+            section_size("attributes", self.attributes.len(), ATTRIBUTE_SIZE)?,
+            1,
+        )?;
+        let mapped_entries = next_section(
+            "mapped_entries",
+            &attributes,
+            section_size(
+                "mapped_entries",
+                self.mapped_entries.len(),
+                MAPPED_ENTRY_SIZE,
+            )?,
+            1,
+        )?;
+        let string_names = next_section(
+            "string_names",
+            &mapped_entries,
+            section_size("string_names", self.string_names.data.len(), 1)?,
+            ALIGNMENT,
+        )?;
+        let string_values = next_section(
+            "string_values",
+            &string_names,
+            section_size("string_values", self.string_values.data.len(), 1)?,
+            ALIGNMENT,
+        )?;
+
+        Ok(XmbLayout {
+            header,
+            string_offsets,
+            entries,
+            attributes,
+            mapped_entries,
+            string_names,
+            string_values,
+            file_size: string_values
+                .end()
+                .checked_next_multiple_of(ALIGNMENT)
+                .ok_or(LayoutOverflowError("string_values"))?,
+        })
+    }
+}
+
+impl XmbLayout {
+    /// Read the sections of an existing file from the offsets in the header.
+    ///
+    /// The values section extends to the end of the file like when reading an [Xmb],
+    /// so its size includes any padding at the end of the file.
+    ///
+    /// Returns [ReadXmbError::Layout] if the counts in the header produce sections past the 32-bit offset range
+    /// or if the values section starts past the end of the file.
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, ReadXmbError> {
+        let start = reader.stream_position()?;
+        let header: Header = reader.read_le()?;
+
+        // The names buffer is only delimited by the number of null bytes.
+        reader.seek(SeekFrom::Start(start + header.string_names_offset as u64))?;
+        let names = NamesBuffer::read_le_args(reader, header.string_count)?;
+
+        let end = reader.seek(SeekFrom::End(0))?;
+        let file_size =
+            u32::try_from(end - start).map_err(|_| LayoutOverflowError("string_values"))?;
+
+        // The header values aren't validated, so check that each section ends within the offset range.
+        let section = |name, offset: u32, count: u32, item_size: u32| {
+            count
+                .checked_mul(item_size)
+                .filter(|size| offset.checked_add(*size).is_some())
+                .map(|size| Section { offset, size })
+                .ok_or(LayoutOverflowError(name))
+        };
+        Ok(Self {
+            header: section("header", 0, HEADER_SIZE, 1)?,
+            string_offsets: section(
+                "string_offsets",
+                header.string_offsets_offset,
+                header.string_count,
+                STRING_OFFSET_SIZE,
+            )?,
+            entries: section(
+                "entries",
+                header.entries_offset,
+                header.entry_count,
+                ENTRY_SIZE,
+            )?,
+            attributes: section(
+                "attributes",
+                header.attributes_offset,
+                header.attribute_count,
+                ATTRIBUTE_SIZE,
+            )?,
+            mapped_entries: section(
+                "mapped_entries",
+                header.mapped_entries_offset,
+                header.mapped_entry_count,
+                MAPPED_ENTRY_SIZE,
+            )?,
+            string_names: section(
+                "string_names",
+                header.string_names_offset,
+                names.data.len() as u32,
+                1,
+            )?,
+            string_values: section(
+                "string_values",
+                header.string_values_offset,
+                file_size
+                    .checked_sub(header.string_values_offset)
+                    .ok_or(LayoutOverflowError("string_values"))?,
+                1,
+            )?,
+            file_size,
+        })
+    }
+}
+
+fn section_size(
+    name: &'static str,
+    count: usize,
+    item_size: u32,
+) -> Result<u32, LayoutOverflowError> {
+    u32::try_from(count)
+        .ok()
+        .and_then(|count| count.checked_mul(item_size))
+        .ok_or(LayoutOverflowError(name))
+}
+
+fn next_section(
+    name: &'static str,
+    previous: &Section,
+    size: u32,
+    alignment: u32,
+) -> Result<Section, LayoutOverflowError> {
+    previous
+        .end()
+        .checked_next_multiple_of(alignment)
+        .filter(|offset| offset.checked_add(size).is_some())
+        .map(|offset| Section { offset, size })
+        .ok_or(LayoutOverflowError(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::XmbFile;
+    use std::io::Cursor;
+
+    fn header_bytes(counts: [u32; 4], offsets: [u32; 6]) -> Vec<u8> {
+        let mut bytes = b"XMB ".to_vec();
+        for value in counts.into_iter().chain(offsets) {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.resize(HEADER_SIZE as usize, 0);
+        bytes
+    }
+
+    #[test]
+    fn read_count_overflow() {
+        let bytes = header_bytes([u32::MAX / 8, 0, 0, 0], [64, 64, 64, 64, 64, 64]);
+        assert!(matches!(
+            XmbLayout::read(&mut Cursor::new(bytes)),
+            Err(ReadXmbError::Layout(LayoutOverflowError("entries")))
+        ));
+    }
+
+    #[test]
+    fn read_offset_overflow() {
+        let bytes = header_bytes([0, 1, 0, 0], [64, 64, u32::MAX - 4, 64, 64, 64]);
+        assert!(matches!(
+            XmbLayout::read(&mut Cursor::new(bytes)),
+            Err(ReadXmbError::Layout(LayoutOverflowError("attributes")))
+        ));
+    }
+
+    #[test]
+    fn read_truncated_values() {
+        // The values section starts past the end of the file.
+        let bytes = header_bytes([0; 4], [64, 64, 64, 64, 64, 128]);
+        assert!(matches!(
+            XmbLayout::read(&mut Cursor::new(bytes)),
+            Err(ReadXmbError::Layout(LayoutOverflowError("string_values")))
+        ));
+    }
+
+    #[test]
+    fn read_empty_sections() {
+        let bytes = header_bytes([0; 4], [64; 6]);
+        let layout = XmbLayout::read(&mut Cursor::new(bytes)).unwrap();
+        let empty = Section {
+            offset: 64,
+            size: 0,
+        };
+        assert_eq!(
+            XmbLayout {
+                header: Section {
+                    offset: 0,
+                    size: HEADER_SIZE
+                },
+                string_offsets: empty,
+                entries: empty,
+                attributes: empty,
+                mapped_entries: empty,
+                string_names: empty,
+                string_values: empty,
+                file_size: 64,
+            },
+            layout
+        );
+    }
+
+    #[test]
+    fn layout_empty_xmb() {
+        let xmb = Xmb::from(&XmbFile {
+            entries: Vec::new(),
+        });
+        let layout = xmb.layout().unwrap();
+        assert_eq!(HEADER_SIZE, layout.string_offsets.offset);
+        assert_eq!(0, layout.entries.size);
+
+        let mut writer = Cursor::new(Vec::new());
+        xmb.write(&mut writer).unwrap();
+        assert_eq!(layout.file_size as usize, writer.get_ref().len());
+    }
+
+    #[test]
+    fn layout_overflow() {
+        // Test the calculations directly to avoid allocating 4 GiB of data.
+        assert!(section_size("entries", u32::MAX as usize / 16 + 1, ENTRY_SIZE).is_err());
+        assert_eq!(
+            u32::MAX - 15,
+            section_size("entries", u32::MAX as usize / 16, ENTRY_SIZE).unwrap()
+        );
+
+        let previous = Section {
+            offset: u32::MAX - 8,
+            size: 6,
+        };
+        assert_eq!(
+            Section {
+                offset: u32::MAX - 2,
+                size: 2
+            },
+            next_section("string_names", &previous, 2, 1).unwrap()
+        );
+        assert!(next_section("string_names", &previous, 3, 1).is_err());
+        assert!(next_section("string_names", &previous, 0, ALIGNMENT).is_err());
+    }
+}
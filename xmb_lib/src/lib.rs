@@ -1,14 +1,34 @@
-use binrw::io::Cursor;
-use indexmap::{IndexMap, IndexSet};
-use std::collections::BTreeMap;
+use builder::{BuildOptions, XmbBuilder};
+use indexmap::IndexMap;
+use meta::XmbMeta;
 use std::convert::{TryFrom, TryInto};
 use std::error::Error;
 use std::io::{Read, Seek, Write};
 use std::path::Path;
 use thiserror::Error;
 use xmb::*;
+use xml::namespace::{
+    Namespace, NamespaceStack, NS_NO_PREFIX, NS_XMLNS_PREFIX, NS_XMLNS_URI, NS_XML_PREFIX,
+    NS_XML_URI,
+};
+use xml::reader::{EventReader, ParserConfig, XmlEvent};
 use xmltree::{Element, XMLNode};
 
+pub mod builder;
+pub mod canonical;
+pub mod collation;
+pub mod document;
+pub mod edit;
+mod emitter;
+pub mod events;
+pub mod hash;
+pub mod index;
+pub mod layout;
+mod macros;
+pub mod meta;
+pub mod repair;
+pub mod verify;
+pub mod visit;
 pub mod xmb;
 
 #[derive(Debug, Error)]
@@ -20,12 +40,68 @@ pub enum CreateXmlError {
     MultipleRootElements,
 }
 
+#[derive(Debug, Error)]
+pub enum CreateXmbError {
+    #[error("XML contains content that can't be stored in XMB: {}", format_dropped_xml(.0))]
+    UnsupportedContent(Vec<DroppedXml>),
+}
+
+fn format_dropped_xml(dropped: &[DroppedXml]) -> String {
+    dropped
+        .iter()
+        .map(|d| d.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[derive(Debug, Error)]
+pub enum ReadXmlError {
+    #[error("failed to parse XML")]
+    Xml(#[from] xmltree::ParseError),
+
+    #[error(transparent)]
+    CreateXmb(#[from] CreateXmbError),
+}
+
+#[derive(Debug, Error)]
+pub enum CompileXmlError {
+    #[error("failed to parse XML")]
+    Xml(#[from] xml::reader::Error),
+
+    #[error(transparent)]
+    CreateXmb(#[from] CreateXmbError),
+}
+
+#[derive(Debug, Error)]
+pub enum WriteXmlError {
+    #[error(transparent)]
+    CreateXml(#[from] CreateXmlError),
+
+    #[error(transparent)]
+    Read(#[from] ReadXmbError),
+
+    #[error("failed to write XML")]
+    Xml(#[from] xml::writer::Error),
+}
+
 #[derive(Debug, Error)]
 pub enum ReadXmbError {
     // TODO: Create meaningful error variants.
     #[error("encountered a null pointer")]
     NullError,
 
+    #[error("failed to read the name or attributes of entry {0}")]
+    InvalidEntry(usize),
+
+    #[error("failed to read the string at offset {0}")]
+    InvalidString(u32),
+
+    #[error("the section at offset {0} overlaps the next section or the end of the file")]
+    InvalidSection(u32),
+
+    #[error(transparent)]
+    Layout(#[from] layout::LayoutOverflowError),
+
     #[error("failed to parse XMB binary")]
     BinRead(#[from] binrw::Error),
 
@@ -33,9 +109,112 @@ pub enum ReadXmbError {
     Io(#[from] std::io::Error),
 }
 
+/// XML content discarded by [XmbFile::from_xml] since it can't be stored in XMB.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DroppedXml {
+    /// The names of the containing element and its ancestors separated by `/` like `"model/draw"`.
+    pub path: String,
+    pub kind: DroppedXmlKind,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum DroppedXmlKind {
+    Text(String),
+    CData(String),
+    /// An attribute with the same name as a namespace declaration or a previous attribute.
+    Attribute {
+        name: String,
+        value: String,
+    },
+}
+
+impl std::fmt::Display for DroppedXml {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            DroppedXmlKind::Text(text) => write!(f, "text {:?} in {}", text, self.path),
+            DroppedXmlKind::CData(text) => write!(f, "CDATA {:?} in {}", text, self.path),
+            DroppedXmlKind::Attribute { name, value } => {
+                write!(f, "attribute {}={:?} in {}", name, value, self.path)
+            }
+        }
+    }
+}
+
+/// Options for [XmbFile::from_xml_with_options].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FromXmlOptions {
+    /// Return an error instead of discarding XML content that can't be stored in XMB.
+    pub strict: bool,
+}
+
+/// Formatting options for writing XML text like [XmbFile::write_xml].
+///
+/// The default is [XmlOptions::ssbu_tools].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct XmlOptions {
+    /// The string for each level of indentation or `None` to write without line breaks.
+    pub indent: Option<String>,
+    /// How to write elements without children.
+    pub empty_elements: EmptyElementStyle,
+    /// Write a declaration like `<?xml version="1.0" encoding="UTF-8"?>` before the root element.
+    pub declaration: bool,
+    /// The encoding name in the declaration.
+    /// The text is always UTF-8, so this only changes the spelling.
+    pub encoding: XmlEncoding,
+}
+
+impl XmlOptions {
+    /// The formatting used by the CLI to match the Python script for SSBU-Tools where possible.
+    /// Elements are indented with four spaces and empty elements are written like `<a/>`.
+    pub fn ssbu_tools() -> Self {
+        Self {
+            indent: Some("    ".to_string()),
+            empty_elements: EmptyElementStyle::SelfClosing,
+            declaration: true,
+            encoding: XmlEncoding::Utf8,
+        }
+    }
+}
+
+impl Default for XmlOptions {
+    fn default() -> Self {
+        Self::ssbu_tools()
+    }
+}
+
+/// The spelling of the UTF-8 encoding name in the declaration for [XmlOptions].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum XmlEncoding {
+    /// `encoding="UTF-8"`
+    Utf8,
+    /// `encoding="utf-8"`
+    Utf8Lowercase,
+}
+
+impl XmlEncoding {
+    /// The encoding name like `"UTF-8"`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            XmlEncoding::Utf8 => "UTF-8",
+            XmlEncoding::Utf8Lowercase => "utf-8",
+        }
+    }
+}
+
+/// How to write elements without children in [XmlOptions].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EmptyElementStyle {
+    /// `<a/>`
+    SelfClosing,
+    /// `<a />`
+    PaddedSelfClosing,
+    /// `<a></a>`
+    StartEnd,
+}
+
 // TODO: Deserialize?
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct XmbFileEntry {
     pub name: String,
     pub attributes: IndexMap<String, String>,
@@ -55,12 +234,142 @@ impl<'a> arbitrary::Arbitrary<'a> for XmbFileEntry {
 
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct XmbFile {
     pub entries: Vec<XmbFileEntry>,
 }
 
+/// Methods for building and editing entries.
+/// # Examples
+/**
+```rust
+use xmb_lib::XmbFileEntry;
+
+let mut entry = XmbFileEntry::new("draw")
+    .child(XmbFileEntry::new("draw").attr("buffer", "0").attr("type", "main"))
+    .child(XmbFileEntry::new("draw").attr("action", "1").attr("type", "normalmap"));
+
+entry.remove_where(|e| e.get_attr("type") == Some("normalmap"));
+entry.child_mut_by_name("draw").unwrap().set_attr("buffer", "1");
+```
+ */
+impl XmbFileEntry {
+    /// Create an entry with no attributes or children.
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            attributes: IndexMap::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Add or replace the attribute `name` and return the updated entry.
+    pub fn attr<K: Into<String>, V: Into<String>>(mut self, name: K, value: V) -> Self {
+        self.set_attr(name, value);
+        self
+    }
+
+    /// Add `child` after the existing children and return the updated entry.
+    pub fn child(mut self, child: XmbFileEntry) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// Add `children` after the existing children and return the updated entry.
+    pub fn children<I: IntoIterator<Item = XmbFileEntry>>(mut self, children: I) -> Self {
+        self.children.extend(children);
+        self
+    }
+
+    pub fn rename<S: Into<String>>(&mut self, name: S) {
+        self.name = name.into();
+    }
+
+    pub fn get_attr(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+
+    /// Set the value for the attribute `name` and return the previous value.
+    /// Existing attributes keep their position.
+    pub fn set_attr<K: Into<String>, V: Into<String>>(
+        &mut self,
+        name: K,
+        value: V,
+    ) -> Option<String> {
+        self.attributes.insert(name.into(), value.into())
+    }
+
+    /// Remove the attribute `name` and return its value.
+    /// The remaining attributes keep their order.
+    pub fn remove_attr(&mut self, name: &str) -> Option<String> {
+        self.attributes.shift_remove(name)
+    }
+
+    /// The first child with the given `name`.
+    pub fn child_by_name(&self, name: &str) -> Option<&XmbFileEntry> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    /// The first child with the given `name`.
+    pub fn child_mut_by_name(&mut self, name: &str) -> Option<&mut XmbFileEntry> {
+        self.children.iter_mut().find(|c| c.name == name)
+    }
+
+    /// All children with the given `name` in order.
+    pub fn children_by_name<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a XmbFileEntry> + 'a {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+
+    /// Insert `child` at position `index` and shift later children to the right.
+    ///
+    /// # Panics
+    /// Panics if `index` is greater than the number of children.
+    pub fn insert_child(&mut self, index: usize, child: XmbFileEntry) {
+        self.children.insert(index, child);
+    }
+
+    /// Add `child` after the existing children.
+    pub fn push_child(&mut self, child: XmbFileEntry) {
+        self.children.push(child);
+    }
+
+    /// Remove the children matching `predicate` and return them in order.
+    pub fn remove_where<F: FnMut(&XmbFileEntry) -> bool>(
+        &mut self,
+        mut predicate: F,
+    ) -> Vec<XmbFileEntry> {
+        let (removed, kept) = std::mem::take(&mut self.children)
+            .into_iter()
+            .partition(|c| predicate(c));
+        self.children = kept;
+        removed
+    }
+
+    /// Move the child at position `from` to position `to` and shift the children in between.
+    ///
+    /// # Panics
+    /// Panics if `from` or `to` are out of bounds.
+    pub fn move_child(&mut self, from: usize, to: usize) {
+        let child = self.children.remove(from);
+        self.children.insert(to, child);
+    }
+}
+
 impl XmbFile {
+    /// The first entry in [entries](#structfield.entries).
+    /// This is the only entry for files converted from XML.
+    pub fn root(&self) -> Option<&XmbFileEntry> {
+        self.entries.first()
+    }
+
+    /// The first entry in [entries](#structfield.entries).
+    pub fn root_mut(&mut self) -> Option<&mut XmbFileEntry> {
+        self.entries.first_mut()
+    }
+
     pub fn to_xml(&self) -> Result<Element, CreateXmlError> {
         let mut root_entries = self.entries.iter();
         let entry = root_entries
@@ -71,15 +380,60 @@ impl XmbFile {
             return Err(CreateXmlError::MultipleRootElements);
         }
 
-        Ok(create_element_recursive(entry))
+        Ok(create_element_recursive(
+            entry,
+            &NamespaceStack::default().squash(),
+        ))
+    }
+
+    /// Convert to XML like [to_xml](#method.to_xml) and insert the comments and processing instructions from `meta`.
+    /// Nodes whose path doesn't match an entry are ignored.
+    pub fn to_xml_with_meta(&self, meta: &XmbMeta) -> Result<Element, CreateXmlError> {
+        let mut element = self.to_xml()?;
+        meta::merge_meta(&mut element, meta);
+        Ok(element)
+    }
+
+    /// Convert the tree of `root` to entries.
+    /// Text and CDATA can't be stored in XMB and are returned instead.
+    /// Namespace prefixes are kept in names like `prefix:name` with declarations stored as `xmlns` attributes.
+    /// Comments and processing instructions are preserved by [XmbMeta].
+    ///
+    /// [Element::parse] discards the prefixes of attribute names,
+    /// so use [parse_xml] to preserve attributes like `ui:c`.
+    pub fn from_xml(root: &Element) -> Result<(Self, Vec<DroppedXml>), CreateXmbError> {
+        Self::from_xml_with_options(root, &FromXmlOptions::default())
     }
 
-    pub fn from_xml(root: &Element) -> Self {
+    /// Convert the tree of `root` to entries like [from_xml](#method.from_xml).
+    /// Returns [CreateXmbError::UnsupportedContent] if any content is discarded in strict mode.
+    pub fn from_xml_with_options(
+        root: &Element,
+        options: &FromXmlOptions,
+    ) -> Result<(Self, Vec<DroppedXml>), CreateXmbError> {
         // TODO: Multiple root nodes?
-        let root = create_entry_from_xml_recursive(root);
-        Self {
-            entries: vec![root],
+        let mut dropped = Vec::new();
+        let root = create_entry_from_xml_recursive(root, None, &mut Vec::new(), &mut dropped);
+
+        if options.strict && !dropped.is_empty() {
+            return Err(CreateXmbError::UnsupportedContent(dropped));
         }
+
+        Ok((
+            Self {
+                entries: vec![root],
+            },
+            dropped,
+        ))
+    }
+
+    /// Parse the XML document in `text` and convert it to entries like [from_xml_with_options](#method.from_xml_with_options).
+    pub fn from_xml_str(
+        text: &str,
+        options: &FromXmlOptions,
+    ) -> Result<(Self, Vec<DroppedXml>), ReadXmlError> {
+        let root = parse_xml(text.as_bytes())?;
+        Self::from_xml_with_options(&root, options).map_err(Into::into)
     }
 
     pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, Box<dyn Error>> {
@@ -90,6 +444,25 @@ impl XmbFile {
         Xmb::from_file(path)?.try_into().map_err(Into::into)
     }
 
+    /// Convert the entries to [Xmb] like [Xmb::from] with the given `options`.
+    pub fn to_xmb(&self, options: &BuildOptions) -> Xmb {
+        // The builder borrows the strings to avoid copying them.
+        let mut builder = XmbBuilder::with_options(options.clone());
+        for entry in &self.entries {
+            builder.add_entry(entry);
+        }
+        builder.finish()
+    }
+
+    /// Convert the entries to [Xmb] like [to_xmb](#method.to_xmb) without copying the strings.
+    pub fn into_xmb(self, options: &BuildOptions) -> Xmb {
+        let mut builder = XmbBuilder::with_options(options.clone());
+        for entry in self.entries {
+            builder.add_owned_entry(entry);
+        }
+        builder.finish()
+    }
+
     pub fn write<W: Write + Seek>(&self, writer: &mut W) -> std::io::Result<()> {
         Xmb::from(self).write(writer)
     }
@@ -99,408 +472,374 @@ impl XmbFile {
     }
 }
 
-// TODO: All these conversions can use test cases.
-fn create_entry_from_xml_recursive(xml_node: &Element) -> XmbFileEntry {
-    let children = xml_node
-        .children
-        .iter()
-        .filter_map(XMLNode::as_element)
-        .map(create_entry_from_xml_recursive)
-        .collect();
-
-    XmbFileEntry {
-        name: xml_node.name.clone(),
-        attributes: xml_node.attributes.clone(),
-        children,
+/// Parse the root element of the XML document from `reader` like [Element::parse].
+///
+/// [Element::parse] only keeps the local name of attributes,
+/// so `c="7" ui:c="8"` would be read as a single attribute `c="8"`.
+/// This keeps the prefix in attribute names like `prefix:name` to match the element names from [XmbFile::from_xml].
+pub fn parse_xml<R: Read>(reader: R) -> Result<Element, xmltree::ParseError> {
+    let config = ParserConfig::new().ignore_comments(false);
+
+    // Nodes outside the root element are skipped like Element::parse.
+    let mut elements: Vec<Element> = Vec::new();
+    for event in EventReader::new_with_config(reader, config) {
+        let node = match event.map_err(xmltree::ParseError::MalformedXml)? {
+            XmlEvent::StartElement {
+                name,
+                attributes,
+                namespace,
+            } => {
+                let mut element = Element::new(&name.local_name);
+                element.prefix = name.prefix;
+                element.namespace = name.namespace;
+                element.namespaces = (!namespace.is_essentially_empty()).then_some(namespace);
+                element.attributes = attributes
+                    .into_iter()
+                    .map(|a| {
+                        let name = qualified_name(a.name.prefix.as_deref(), &a.name.local_name);
+                        (name, a.value)
+                    })
+                    .collect();
+                elements.push(element);
+                continue;
+            }
+            XmlEvent::EndElement { .. } => {
+                let element = elements.pop().ok_or(xmltree::ParseError::CannotParse)?;
+                if elements.is_empty() {
+                    return Ok(element);
+                }
+                XMLNode::Element(element)
+            }
+            XmlEvent::Characters(text) => XMLNode::Text(text),
+            XmlEvent::CData(text) => XMLNode::CData(text),
+            XmlEvent::Comment(text) => XMLNode::Comment(text),
+            XmlEvent::ProcessingInstruction { name, data } => {
+                XMLNode::ProcessingInstruction(name, data)
+            }
+            _ => continue,
+        };
+        if let Some(parent) = elements.last_mut() {
+            parent.children.push(node);
+        }
     }
+
+    Err(xmltree::ParseError::CannotParse)
 }
 
-impl TryFrom<Xmb> for XmbFile {
-    type Error = ReadXmbError;
+// TODO: All these conversions can use test cases.
+fn create_entry_from_xml_recursive<'a>(
+    xml_node: &'a Element,
+    parent: Option<&'a Element>,
+    path: &mut Vec<String>,
+    dropped: &mut Vec<DroppedXml>,
+) -> XmbFileEntry {
+    let name = qualified_name(xml_node.prefix.as_deref(), &xml_node.name);
+    path.push(name.clone());
+
+    let mut attributes: IndexMap<_, _> = namespace_declaration_attributes(
+        xml_node.namespaces.as_ref(),
+        parent.and_then(|p| p.namespaces.as_ref()),
+    )
+    .collect();
+    for (k, v) in &xml_node.attributes {
+        insert_attribute(&mut attributes, k.clone(), v.clone(), path, dropped);
+    }
 
-    fn try_from(xmb: Xmb) -> Result<Self, Self::Error> {
-        Self::try_from(&xmb)
+    let mut children = Vec::new();
+    for child in &xml_node.children {
+        match child {
+            XMLNode::Element(e) => children.push(create_entry_from_xml_recursive(
+                e,
+                Some(xml_node),
+                path,
+                dropped,
+            )),
+            XMLNode::Text(text) => add_dropped(dropped, path, DroppedXmlKind::Text(text.clone())),
+            XMLNode::CData(text) => add_dropped(dropped, path, DroppedXmlKind::CData(text.clone())),
+            XMLNode::Comment(_) | XMLNode::ProcessingInstruction(_, _) => (),
+        }
     }
-}
 
-// TODO: Make a separate XmbError without IO errors?
-impl TryFrom<&Xmb> for XmbFile {
-    type Error = ReadXmbError;
+    path.pop();
 
-    fn try_from(xmb: &Xmb) -> Result<Self, Self::Error> {
-        create_xmb_file(xmb).ok_or(ReadXmbError::NullError)
+    XmbFileEntry {
+        name,
+        attributes,
+        children,
     }
 }
 
-#[derive(Debug, Clone)]
-struct XmbEntryTemp {
+// Report attributes with the same name as a namespace declaration instead of replacing its value.
+fn insert_attribute(
+    attributes: &mut IndexMap<String, String>,
     name: String,
-    attributes: Vec<(String, String)>,
-    parent_index: Option<usize>,
-    child_count: usize,
-    index: usize,
-}
-
-// TODO: Is this just BFS order?
-// Create temp types to flatten the list before writing offsets.
-// This avoids leaving structs partially initialized.
-// TODO: Is there a way to avoid this extra step?
-fn add_temp_entries_recursive(
-    children: &[XmbFileEntry],
-    temp_entries: &mut Vec<XmbEntryTemp>,
-    parent_index: Option<usize>,
+    value: String,
+    path: &[String],
+    dropped: &mut Vec<DroppedXml>,
 ) {
-    let new_children: Vec<_> = children
-        .iter()
-        .enumerate()
-        .map(|(i, child)| {
-            // Rust strings allow null bytes but XMB does not.
-            // For now, just strip nulls.
-            XmbEntryTemp {
-                name: child.name.replace('\0', ""),
-                attributes: child
-                    .attributes
-                    .iter()
-                    .map(|(k, v)| (k.replace('\0', ""), v.replace('\0', "")))
-                    .collect(),
-                parent_index,
-                child_count: child.children.len(),
-                index: temp_entries.len() + i,
-            }
-        })
-        .collect();
-
-    // Create a copy just as a way to know the parent index.
-    // TODO: This is pretty inefficient.
-    temp_entries.extend(new_children.clone());
-
-    for (child, temp) in children.iter().zip(new_children) {
-        add_temp_entries_recursive(&child.children, temp_entries, Some(temp.index));
+    match attributes.entry(name) {
+        indexmap::map::Entry::Occupied(e) => {
+            let name = e.key().clone();
+            add_dropped(dropped, path, DroppedXmlKind::Attribute { name, value });
+        }
+        indexmap::map::Entry::Vacant(e) => {
+            e.insert(value);
+        }
     }
 }
 
-// TODO: Find a way to test this conversion.
-// TODO: This should be try_from or it's own method.
-impl From<&XmbFile> for Xmb {
-    fn from(xmb_file: &XmbFile) -> Self {
-        // TODO: This could be more efficient by owning the XmbFile to avoid copying strings.
-
-        // Flatten the tree by iterating in the expected entry order in the XMB file.
-        let mut flattened_temp_entries = Vec::new();
-        add_temp_entries_recursive(&xmb_file.entries, &mut flattened_temp_entries, None);
-
-        // Collect unique names and values as they appear in the flattened entries.
-        // TODO: This can also initialize the offsets and string buffers.
-        // TODO: Is this used for some sort of lookup?
-        let mut names = IndexSet::new();
-        let mut values = IndexSet::new();
-        for entry in &flattened_temp_entries {
-            names.insert(entry.name.clone());
-            for (k, v) in &entry.attributes {
-                names.insert(k.clone());
-                values.insert(v);
-            }
-        }
-
-        // Use these names to initialize the offsets.
-        // It makes sense to make the buffers and offsets at the same time.
-        // This avoids relying on string length.
-        // TODO: Avoid unwrap.
-        let mut string_offsets = BTreeMap::new();
-        let mut names_buffer = Cursor::new(Vec::new());
-        for name in names {
-            let offset = names_buffer.stream_position().unwrap();
-            string_offsets.insert(name.clone(), offset as u32);
-
-            // TODO: This writes additional null bytes for empty strings?
-            // TODO: Empty strings won't have a null byte?
-            // TODO: This will only read one null byte when reading a new xmb even if we write more?
-            names_buffer.write_all(name.as_bytes()).unwrap();
-            names_buffer.write_all(&[0u8]).unwrap();
-        }
+fn add_dropped(dropped: &mut Vec<DroppedXml>, path: &[String], kind: DroppedXmlKind) {
+    dropped.push(DroppedXml {
+        path: path.join("/"),
+        kind,
+    })
+}
 
-        let mut values_offsets = BTreeMap::new();
-        let mut values_buffer = Cursor::new(Vec::new());
-        for value in values {
-            let offset = values_buffer.stream_position().unwrap();
-            values_offsets.insert(value.clone(), offset as u32);
+// Preserve namespaces using the qualified name like prefix:name.
+fn qualified_name(prefix: Option<&str>, name: &str) -> String {
+    match prefix {
+        Some(prefix) => format!("{prefix}:{name}"),
+        None => name.to_string(),
+    }
+}
 
-            values_buffer.write_all(value.as_bytes()).unwrap();
-            values_buffer.write_all(&[0u8]).unwrap();
-        }
+// Find the namespaces added by this element that aren't declared by its parent.
+// Declarations are stored as attributes like xmlns:prefix="uri".
+fn namespace_declaration_attributes<'a>(
+    namespaces: Option<&'a Namespace>,
+    parent_namespaces: Option<&'a Namespace>,
+) -> impl Iterator<Item = (String, String)> + 'a {
+    namespaces
+        .into_iter()
+        .flat_map(|n| n.0.iter())
+        .filter(move |(prefix, uri)| {
+            // The reserved xml and xmlns prefixes are always in scope.
+            !matches!(
+                (prefix.as_str(), uri.as_str()),
+                (NS_NO_PREFIX, "") | (NS_XML_PREFIX, NS_XML_URI) | (NS_XMLNS_PREFIX, NS_XMLNS_URI)
+            ) && parent_namespaces.and_then(|n| n.get(prefix.as_str())) != Some(uri.as_str())
+        })
+        .map(|(prefix, uri)| {
+            let name = if prefix == NS_NO_PREFIX {
+                NS_XMLNS_PREFIX.to_string()
+            } else {
+                format!("{NS_XMLNS_PREFIX}:{prefix}")
+            };
+            (name, uri.clone())
+        })
+}
 
-        // Collect all entries and attributes.
-        let mut attributes = Vec::new();
+/// Convert the XML document from `reader` to [Xmb] without creating an [Element] or [XmbFile].
+///
+/// The result is the same as [XmbFile::from_xml_with_options] followed by [Xmb::from],
+/// but only the entry tree and a single copy of each unique string are kept while parsing.
+/// This uses substantially less memory and time for large documents.
+pub fn compile_xml<R: Read>(
+    reader: R,
+    options: &FromXmlOptions,
+) -> Result<(Xmb, Vec<DroppedXml>), CompileXmlError> {
+    // Use the same parser settings as Element::parse to handle text the same way.
+    let config = ParserConfig::new().ignore_comments(false);
+
+    let mut builder = XmbBuilder::new();
+    let mut dropped = Vec::new();
+
+    // Track the names and namespaces of the open elements.
+    let mut path = Vec::new();
+    let mut namespaces: Vec<Namespace> = Vec::new();
+    let mut is_root_finished = false;
+
+    for event in EventReader::new_with_config(reader, config) {
+        match event? {
+            // Only the first root element is converted like Element::parse.
+            XmlEvent::StartElement { .. } | XmlEvent::EndElement { .. } if is_root_finished => (),
+            XmlEvent::StartElement {
+                name,
+                attributes,
+                namespace,
+            } => {
+                let name = qualified_name(name.prefix.as_deref(), &name.local_name);
+                path.push(name.clone());
+
+                let mut entry_attributes: IndexMap<_, _> =
+                    namespace_declaration_attributes(Some(&namespace), namespaces.last()).collect();
+                for attribute in attributes {
+                    let attribute_name = qualified_name(
+                        attribute.name.prefix.as_deref(),
+                        &attribute.name.local_name,
+                    );
+                    insert_attribute(
+                        &mut entry_attributes,
+                        attribute_name,
+                        attribute.value,
+                        &path,
+                        &mut dropped,
+                    );
+                }
 
-        // Collect strings for id attributes and corresponding node indices.
-        // The lookup is sorted alphabetically by the "id" value.
-        let mut entry_index_by_id = BTreeMap::new();
-        for (i, temp_entry) in flattened_temp_entries.iter().enumerate() {
-            // Assume only the "id" attribute is used for lookups.
-            // This seems to be the case for Smash Ultimate and Smash 4.
-            let id_value = temp_entry
-                .attributes
-                .iter()
-                .find(|(k, _v)| k == "id")
-                .map(|(_k, v)| v);
+                builder.start_entry(name, entry_attributes);
+                namespaces.push(namespace);
+            }
+            XmlEvent::EndElement { .. } => {
+                builder.end_entry();
 
-            if let Some(id_value) = id_value {
-                entry_index_by_id.insert(id_value, i);
+                path.pop();
+                namespaces.pop();
+                is_root_finished = path.is_empty();
             }
+            XmlEvent::Characters(text) if !path.is_empty() => {
+                add_dropped(&mut dropped, &path, DroppedXmlKind::Text(text))
+            }
+            XmlEvent::CData(text) if !path.is_empty() => {
+                add_dropped(&mut dropped, &path, DroppedXmlKind::CData(text))
+            }
+            _ => (),
         }
+    }
 
-        let mapped_entries: Vec<_> = entry_index_by_id
-            .iter()
-            .map(|(id_value, entry_index)| MappedEntry {
-                value_offset: *values_offsets.get(*id_value).unwrap(),
-                entry_index: *entry_index as u32,
-            })
-            .collect();
-
-        let mut entries = Vec::new();
-        for temp_entry in &flattened_temp_entries {
-            let attribute_start_index = if temp_entry.attributes.is_empty() {
-                -1
-            } else {
-                attributes.len() as i16
-            };
-
-            let unk1 = calculate_unk1(temp_entry, &flattened_temp_entries) as i16;
+    if options.strict && !dropped.is_empty() {
+        return Err(CreateXmbError::UnsupportedContent(dropped).into());
+    }
 
-            let entry_attributes: Vec<_> = temp_entry
-                .attributes
-                .iter()
-                .map(|(k, v)| Attribute {
-                    name_offset: *string_offsets.get(k).unwrap(),
-                    value_offset: *values_offsets.get(v).unwrap(),
-                })
-                .collect();
-
-            let entry = Entry {
-                name_offset: *string_offsets.get(&temp_entry.name).unwrap(),
-                attribute_count: entry_attributes.len() as u16,
-                child_count: temp_entry.child_count as u16,
-                attribute_start_index,
-                unk1,
-                parent_index: temp_entry.parent_index.map(|i| i as i16).unwrap_or(-1),
-                unk2: -1,
-            };
-            entries.push(entry);
+    Ok((builder.finish(), dropped))
+}
 
-            attributes.extend(entry_attributes);
-        }
+impl TryFrom<Xmb> for XmbFile {
+    type Error = ReadXmbError;
 
-        Self {
-            entry_count: entries.len() as u32,
-            attribute_count: attributes.len() as u32,
-            string_count: string_offsets.len() as u32,
-            mapped_entry_count: mapped_entries.len() as u32,
-            string_offsets: string_offsets.values().copied().collect(),
-            entries,
-            attributes,
-            mapped_entries,
-            string_names: NamesBuffer {
-                data: names_buffer.into_inner(),
-            },
-            string_values: ValuesBuffer {
-                data: values_buffer.into_inner(),
-            },
-            unks: [0; 5],
-        }
+    fn try_from(xmb: Xmb) -> Result<Self, Self::Error> {
+        Self::try_from(&xmb)
     }
 }
 
-// TODO: This is probably not the simplest or most efficient way to write this.
-fn calculate_unk1(entry: &XmbEntryTemp, flattened_temp_entries: &[XmbEntryTemp]) -> usize {
-    // TODO: Create a function to find children?
-    let child_indices: Vec<_> = flattened_temp_entries
-        .iter()
-        .filter(|c| c.parent_index == Some(entry.index))
-        .map(|c| c.index)
-        .collect();
+// TODO: Make a separate XmbError without IO errors?
+impl TryFrom<&Xmb> for XmbFile {
+    type Error = ReadXmbError;
 
-    match child_indices.first() {
-        Some(first_child) => *first_child,
-        None => {
-            let parent = find_parent(entry, flattened_temp_entries);
-            calculate_unk1_leaf(entry, parent, flattened_temp_entries)
-                .unwrap_or(flattened_temp_entries.len())
-        }
+    fn try_from(xmb: &Xmb) -> Result<Self, Self::Error> {
+        create_xmb_file(xmb).ok_or(ReadXmbError::NullError)
     }
 }
 
-fn calculate_unk1_leaf(
-    entry: &XmbEntryTemp,
-    parent: Option<&XmbEntryTemp>,
-    flattened_temp_entries: &[XmbEntryTemp],
-) -> Option<usize> {
-    // Cover the base case by returning None if there is no parent.
-    // For the rightmost node at the leaf level, this will traverse up the tree.
-    // The root node has no parent and will return None.
-    let parent = parent?;
-
-    // TODO: It might be simpler to just match on find_next_sibling for parent.
-    match find_parent(parent, flattened_temp_entries) {
-        Some(grand_parent) => {
-            let next_sibling = find_next_sibling(parent, flattened_temp_entries);
-
-            // TODO: There's a case for some lod.xmb files where this can return -1?
-
-            // Use the first child of the parent's next sibling.
-            // If this doesn't work, recurse up the tree.
-            match next_sibling {
-                Some(next_sibling) => {
-                    // TODO: Reuse this find children function?
-                    let child_indices: Vec<_> = flattened_temp_entries
-                        .iter()
-                        .filter(|c| c.parent_index == Some(next_sibling.index))
-                        .map(|c| c.index)
-                        .collect();
-
-                    match child_indices.first() {
-                        Some(first_child) => Some(*first_child),
-                        None => {
-                            calculate_unk1_leaf(entry, Some(grand_parent), flattened_temp_entries)
-                        }
-                    }
-                }
-                None => calculate_unk1_leaf(entry, Some(grand_parent), flattened_temp_entries),
-            }
-        }
-        None => {
-            // It's possible for only some of a node's children to be leaves (no children).
-            // This case comes up in some model.xmb files.
-            let next_sibling = find_next_sibling_with_children(entry, flattened_temp_entries);
-            next_sibling.map(|s| s.index)
-        }
+// TODO: Find a way to test this conversion.
+// TODO: This should be try_from or it's own method.
+impl From<&XmbFile> for Xmb {
+    fn from(xmb_file: &XmbFile) -> Self {
+        xmb_file.to_xmb(&BuildOptions::default())
     }
 }
 
-// TODO: These can be methods on XmbEntryTemp.
-fn find_parent<'a>(
-    entry: &'a XmbEntryTemp,
-    flattened_temp_entries: &'a [XmbEntryTemp],
-) -> Option<&'a XmbEntryTemp> {
-    entry
-        .parent_index
-        .and_then(|i| flattened_temp_entries.get(i))
+impl From<XmbFile> for Xmb {
+    fn from(xmb_file: XmbFile) -> Self {
+        Xmb::from_owned(xmb_file)
+    }
 }
 
-fn find_next_sibling<'a>(
-    entry: &'a XmbEntryTemp,
-    flattened_temp_entries: &'a [XmbEntryTemp],
-) -> Option<&'a XmbEntryTemp> {
-    let parent_index = entry.parent_index?;
-
-    let siblings: Vec<_> = flattened_temp_entries
-        .iter()
-        .filter(|c| c.parent_index == Some(parent_index))
-        .collect();
-
-    let sibling_index = siblings
-        .iter()
-        .position(|s| s.index == entry.index)
-        .unwrap();
-
-    siblings.get(sibling_index + 1).copied()
+impl Xmb {
+    /// Convert `xmb_file` to binary data with the default [BuildOptions].
+    /// This avoids copying strings when the entries are no longer needed.
+    pub fn from_owned(xmb_file: XmbFile) -> Self {
+        xmb_file.into_xmb(&BuildOptions::default())
+    }
 }
 
-fn find_next_sibling_with_children<'a>(
-    entry: &'a XmbEntryTemp,
-    flattened_temp_entries: &'a [XmbEntryTemp],
-) -> Option<&'a XmbEntryTemp> {
-    let parent_index = entry.parent_index?;
-    let siblings: Vec<_> = flattened_temp_entries
-        .iter()
-        .filter(|c| c.parent_index == Some(parent_index))
-        .collect();
-
-    let sibling_index = siblings
-        .iter()
-        .position(|s| s.index == entry.index)
-        .unwrap();
-
-    // Find the first child of the next sibling after the current entry with children.
-    siblings
-        .iter()
-        .skip(sibling_index + 1)
-        .filter_map(|c| {
-            let first_child = flattened_temp_entries
-                .iter()
-                .find(|c1| c1.parent_index == Some(c.index));
-            first_child
-        })
-        .next()
-}
+fn create_element_recursive(entry: &XmbFileEntry, parent_namespaces: &Namespace) -> Element {
+    // Namespace declarations are stored as attributes like xmlns:prefix="uri".
+    let mut namespaces = parent_namespaces.clone();
+    let mut attributes = IndexMap::new();
+    for (k, v) in &entry.attributes {
+        match namespace_declaration_prefix(k) {
+            Some(prefix) => {
+                namespaces.force_put(prefix, v.clone());
+            }
+            None => {
+                attributes.insert(k.clone(), v.clone());
+            }
+        }
+    }
 
-fn create_element_recursive(entry: &XmbFileEntry) -> Element {
     // Just create child elements for each mapped entry for now.
     let children: Vec<_> = entry
         .children
         .iter()
-        .map(|e| XMLNode::Element(create_element_recursive(e)))
+        .map(|e| XMLNode::Element(create_element_recursive(e, &namespaces)))
         .collect();
 
+    // Qualified names like prefix:name use the namespace for the prefix.
+    let (prefix, name) = match entry.name.split_once(':') {
+        Some((prefix, name)) => (Some(prefix.to_string()), name.to_string()),
+        None => (None, entry.name.clone()),
+    };
+    let namespace = namespaces
+        .get(prefix.as_deref().unwrap_or(NS_NO_PREFIX))
+        .filter(|uri| !uri.is_empty())
+        .map(Into::into);
+
     xmltree::Element {
-        prefix: None,
-        namespace: None,
-        namespaces: None,
-        name: entry.name.clone(),
-        attributes: entry.attributes.clone(),
+        prefix,
+        namespace,
+        namespaces: (!namespaces.is_essentially_empty()).then_some(namespaces),
+        name,
+        attributes,
         children,
     }
 }
 
+fn namespace_declaration_prefix(attribute: &str) -> Option<&str> {
+    if attribute == NS_XMLNS_PREFIX {
+        Some(NS_NO_PREFIX)
+    } else {
+        attribute
+            .strip_prefix(NS_XMLNS_PREFIX)
+            .and_then(|a| a.strip_prefix(':'))
+    }
+}
+
 fn get_attributes(xmb_data: &Xmb, entry: &Entry) -> Option<IndexMap<String, String>> {
     (0..entry.attribute_count)
         .map(|i| {
             // TODO: Don't perform unchecked arithmetic and indexing with signed numbers.
             let attribute_index = (entry.attribute_start_index as u16 + i) as usize;
             let attribute = &xmb_data.attributes.get(attribute_index)?;
-            let key = xmb_data.read_name(attribute.name_offset)?;
-            let value = xmb_data.read_value(attribute.value_offset)?;
+            let key = read_owned_string(&xmb_data.string_names.data, attribute.name_offset)?;
+            let value = read_owned_string(&xmb_data.string_values.data, attribute.value_offset)?;
             Some((key, value))
         })
         .collect()
 }
 
-// TODO: Try to find a more straightforward iterative approach.
-// It should be doable to iterate the entry list at most twice?
-fn create_children_recursive(
+// Read directly from the buffer to allocate each string only once with the exact length.
+// Repeated names and values can't be interned since XmbFileEntry owns its strings.
+// A cache of decoded strings would still need a clone for each entry and saves no allocations.
+fn read_owned_string(data: &[u8], offset: u32) -> Option<String> {
+    events::read_string(data, offset).map(|s| s.into_owned())
+}
+
+fn create_entry_recursive(
     xmb_data: &Xmb,
-    entry: &Entry,
-    entry_index: i16,
+    index: usize,
+    children: &[Vec<usize>],
 ) -> Option<XmbFileEntry> {
-    let child_entries: Vec<_> = xmb_data
-        .entries
-        .iter()
-        .enumerate()
-        .filter(|(_, e)| e.parent_index == entry_index)
-        .collect();
-
-    let children: Vec<_> = child_entries
-        .iter()
-        .map(|(i, e)| create_children_recursive(xmb_data, e, *i as i16))
-        .collect::<Option<Vec<_>>>()?;
-
+    let entry = &xmb_data.entries[index];
     Some(XmbFileEntry {
-        name: xmb_data.read_name(entry.name_offset)?,
+        name: read_owned_string(&xmb_data.string_names.data, entry.name_offset)?,
         attributes: get_attributes(xmb_data, entry)?,
-        children,
+        children: children[index]
+            .iter()
+            .map(|i| create_entry_recursive(xmb_data, *i, children))
+            .collect::<Option<Vec<_>>>()?,
     })
 }
 
 fn create_xmb_file(xmb_data: &Xmb) -> Option<XmbFile> {
-    // First find the nodes with no parents.
-    // Then recursively add their children based on the parent index.
+    // Find the children of all entries in a single pass over the parent indices.
+    // Entries with an invalid parent index are not reachable from the roots.
     // Assume a null pointer just means no entries.
     // TODO: Return an error instead of an option?
-    let roots: Vec<_> = xmb_data
-        .entries
+    let children = events::entry_children(xmb_data);
+    let roots: Vec<_> = children[xmb_data.entries.len()]
         .iter()
-        .enumerate()
-        .filter(|(_, e)| e.parent_index == -1)
-        .filter_map(|(i, e)| create_children_recursive(xmb_data, e, i as i16))
+        .filter_map(|i| create_entry_recursive(xmb_data, *i, &children))
         .collect();
 
     Some(XmbFile { entries: roots })
@@ -512,8 +851,13 @@ mod tests {
     // XMB is a binary version of XML, so construct XML documents by hand.
     // This tests the necessary format features with substantially smaller test cases.
     use super::*;
+    use builder::StringLayout;
+    use collation::Collation;
+    use document::{DocumentError, NodeId, XmbDocument};
+    use events::XmbEvent;
     use indexmap::indexmap;
     use indoc::indoc;
+    use meta::{MetaNode, MetaNodeKind};
 
     // TODO: Test Xmb <-> XmbFile
 
@@ -530,7 +874,8 @@ mod tests {
         };
         let element = Element::parse(data.as_bytes()).unwrap();
 
-        let xmb_file = XmbFile::from_xml(&element);
+        let (xmb_file, dropped) = XmbFile::from_xml(&element).unwrap();
+        assert!(dropped.is_empty());
         assert_eq!(
             XmbFile {
                 entries: vec![XmbFileEntry {
@@ -566,6 +911,1087 @@ mod tests {
         assert_eq!(element, output_element);
     }
 
+    #[test]
+    fn xmb_file_to_from_xml_meta() {
+        let data = indoc! {r#"
+            <?xml version="1.0" encoding="UTF-8"?>
+            <root a="1">
+                <!-- first -->
+                <child1>
+                    <?target data?>
+                    <subchild1/>
+                    <!-- last
+                    line -->
+                </child1>
+                <!---->
+                <child2/>
+                <!--end-->
+            </root>"#
+        };
+        let element = Element::parse(data.as_bytes()).unwrap();
+
+        let meta = XmbMeta::from_xml(&element);
+        assert_eq!(
+            vec![
+                MetaNode {
+                    path: vec![0],
+                    index: 0,
+                    kind: MetaNodeKind::Comment(" first ".into())
+                },
+                MetaNode {
+                    path: vec![0, 0],
+                    index: 0,
+                    kind: MetaNodeKind::ProcessingInstruction("target".into(), Some("data".into()))
+                },
+                MetaNode {
+                    path: vec![0, 0],
+                    index: 1,
+                    kind: MetaNodeKind::Comment(" last\n        line ".into())
+                },
+                MetaNode {
+                    path: vec![0],
+                    index: 1,
+                    kind: MetaNodeKind::Comment("".into())
+                },
+                MetaNode {
+                    path: vec![0],
+                    index: 2,
+                    kind: MetaNodeKind::Comment("end".into())
+                },
+            ],
+            meta.nodes
+        );
+
+        // The sidecar file should preserve the nodes exactly.
+        let mut writer = std::io::Cursor::new(Vec::new());
+        meta.write(&mut writer).unwrap();
+        let new_meta = XmbMeta::read(std::io::Cursor::new(writer.into_inner())).unwrap();
+        assert_eq!(meta, new_meta);
+
+        let (xmb_file, _) = XmbFile::from_xml(&element).unwrap();
+        let output_element = xmb_file.to_xml_with_meta(&new_meta).unwrap();
+        assert_eq!(element, output_element);
+
+        let config = xmltree::EmitterConfig::new()
+            .perform_indent(true)
+            .indent_string("    ")
+            .pad_self_closing(false);
+        let mut expected = Vec::new();
+        output_element
+            .write_with_config(&mut expected, config)
+            .unwrap();
+
+        let mut actual = Vec::new();
+        xmb_file
+            .write_xml_with_meta(&mut actual, &new_meta, &XmlOptions::default())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(expected).unwrap(),
+            String::from_utf8(actual).unwrap()
+        );
+    }
+
+    #[test]
+    fn xmb_file_from_xml_dropped_content() {
+        let data = indoc! {r#"
+            <?xml version="1.0" encoding="UTF-8"?>
+            <root xmlns:ui="http://example.com/ui">
+                <name>foo</name>
+                <ui:draw><![CDATA[bar]]></ui:draw>
+            </root>"#
+        };
+        let element = Element::parse(data.as_bytes()).unwrap();
+
+        let (_, dropped) = XmbFile::from_xml(&element).unwrap();
+        assert_eq!(
+            vec![
+                DroppedXml {
+                    path: "root/name".into(),
+                    kind: DroppedXmlKind::Text("foo".into())
+                },
+                DroppedXml {
+                    path: "root/ui:draw".into(),
+                    kind: DroppedXmlKind::CData("bar".into())
+                },
+            ],
+            dropped
+        );
+
+        let result = XmbFile::from_xml_with_options(&element, &FromXmlOptions { strict: true });
+        assert!(matches!(
+            result,
+            Err(CreateXmbError::UnsupportedContent(d)) if d == dropped
+        ));
+    }
+
+    #[test]
+    fn xmb_file_to_from_xml_namespaces() {
+        let data = indoc! {r#"
+            <?xml version="1.0" encoding="UTF-8"?>
+            <root xmlns="http://example.com" a="1">
+                <ui:draw xmlns:ui="http://example.com/ui" b="2">
+                    <ui:child/>
+                </ui:draw>
+                <child/>
+            </root>"#
+        };
+        let element = Element::parse(data.as_bytes()).unwrap();
+
+        let (xmb_file, dropped) = XmbFile::from_xml(&element).unwrap();
+        assert!(dropped.is_empty());
+        assert_eq!(
+            XmbFile {
+                entries: vec![XmbFileEntry {
+                    name: "root".into(),
+                    attributes: indexmap![
+                        "xmlns".into() => "http://example.com".into(),
+                        "a".into() => "1".into()
+                    ],
+                    children: vec![
+                        XmbFileEntry {
+                            name: "ui:draw".into(),
+                            attributes: indexmap![
+                                "xmlns:ui".into() => "http://example.com/ui".into(),
+                                "b".into() => "2".into()
+                            ],
+                            children: vec![XmbFileEntry {
+                                name: "ui:child".into(),
+                                attributes: IndexMap::new(),
+                                children: Vec::new(),
+                            }],
+                        },
+                        XmbFileEntry {
+                            name: "child".into(),
+                            attributes: IndexMap::new(),
+                            children: Vec::new(),
+                        }
+                    ],
+                }]
+            },
+            xmb_file
+        );
+
+        let output_element = xmb_file.to_xml().unwrap();
+        assert_eq!(element, output_element);
+    }
+
+    #[test]
+    fn xmb_file_to_from_xml_prefixed_attributes() {
+        let data = indoc! {r#"
+            <?xml version="1.0" encoding="UTF-8"?>
+            <root xmlns:ui="http://example.com/ui" c="7" ui:c="8">
+                <ui:draw ui:c="9"/>
+            </root>"#
+        };
+        let element = parse_xml(data.as_bytes()).unwrap();
+
+        let (xmb_file, dropped) = XmbFile::from_xml(&element).unwrap();
+        assert!(dropped.is_empty());
+        assert_eq!(
+            XmbFile {
+                entries: vec![XmbFileEntry::new("root")
+                    .attr("xmlns:ui", "http://example.com/ui")
+                    .attr("c", "7")
+                    .attr("ui:c", "8")
+                    .child(XmbFileEntry::new("ui:draw").attr("ui:c", "9"))]
+            },
+            xmb_file
+        );
+        assert_eq!(element, xmb_file.to_xml().unwrap());
+
+        // Compiling directly should keep the same prefixes.
+        let (xmb, dropped) = compile_xml(data.as_bytes(), &FromXmlOptions::default()).unwrap();
+        assert!(dropped.is_empty());
+        assert_eq!(xmb_file, XmbFile::try_from(&xmb).unwrap());
+
+        let mut text = Vec::new();
+        xmb.write_xml(&mut text, &XmlOptions::default()).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.contains(r#"<root xmlns:ui="http://example.com/ui" c="7" ui:c="8">"#));
+        assert!(text.contains(r#"<ui:draw ui:c="9"/>"#));
+        assert_eq!(
+            xmb_file,
+            XmbFile::from_xml(&parse_xml(text.as_bytes()).unwrap())
+                .unwrap()
+                .0
+        );
+    }
+
+    #[test]
+    fn xmb_file_from_xml_attribute_collision() {
+        // Element::parse never creates xmlns attributes, but elements can be created directly.
+        let mut element = Element::new("root");
+        let mut namespaces = xml::namespace::Namespace::empty();
+        namespaces.put("ui", "http://example.com/ui");
+        element.namespaces = Some(namespaces);
+        element
+            .attributes
+            .insert("xmlns:ui".into(), "http://example.com/other".into());
+
+        let (xmb_file, dropped) = XmbFile::from_xml(&element).unwrap();
+        assert_eq!(
+            Some("http://example.com/ui"),
+            xmb_file.root().unwrap().get_attr("xmlns:ui")
+        );
+        assert_eq!(
+            vec![DroppedXml {
+                path: "root".into(),
+                kind: DroppedXmlKind::Attribute {
+                    name: "xmlns:ui".into(),
+                    value: "http://example.com/other".into()
+                }
+            }],
+            dropped
+        );
+
+        let result = XmbFile::from_xml_with_options(&element, &FromXmlOptions { strict: true });
+        assert!(matches!(
+            result,
+            Err(CreateXmbError::UnsupportedContent(d)) if d == dropped
+        ));
+    }
+
+    #[test]
+    fn xmb_file_entry_builder() {
+        let entry = XmbFileEntry::new("model")
+            .attr("type", "effect_main")
+            .child(XmbFileEntry::new("shadow").attr("caster", "0"))
+            .children([XmbFileEntry::new("draw").child(
+                XmbFileEntry::new("draw")
+                    .attr("buffer", "0")
+                    .attr("type", "main"),
+            )]);
+
+        assert_eq!(
+            XmbFileEntry {
+                name: "model".into(),
+                attributes: indexmap!["type".into() => "effect_main".into()],
+                children: vec![
+                    XmbFileEntry {
+                        name: "shadow".into(),
+                        attributes: indexmap!["caster".into() => "0".into()],
+                        children: Vec::new()
+                    },
+                    XmbFileEntry {
+                        name: "draw".into(),
+                        attributes: IndexMap::new(),
+                        children: vec![XmbFileEntry {
+                            name: "draw".into(),
+                            attributes: indexmap![
+                                "buffer".into() => "0".into(),
+                                "type".into() => "main".into()
+                            ],
+                            children: Vec::new()
+                        }]
+                    }
+                ]
+            },
+            entry
+        );
+    }
+
+    #[test]
+    fn xmb_file_entry_editing() {
+        let mut entry = XmbFileEntry::new("root")
+            .attr("a", "1")
+            .attr("b", "2")
+            .attr("c", "3")
+            .child(XmbFileEntry::new("child1"))
+            .child(XmbFileEntry::new("child2").attr("remove", "1"))
+            .child(XmbFileEntry::new("child3"));
+
+        assert_eq!(Some("2".into()), entry.set_attr("b", "4"));
+        assert_eq!(None, entry.set_attr("d", "5"));
+        assert_eq!(Some("1".into()), entry.remove_attr("a"));
+        assert_eq!(
+            vec![("b", "4"), ("c", "3"), ("d", "5")],
+            entry
+                .attributes
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect::<Vec<_>>()
+        );
+
+        entry.child_mut_by_name("child3").unwrap().rename("child4");
+        entry.insert_child(0, XmbFileEntry::new("child0"));
+        entry.move_child(3, 1);
+
+        let removed = entry.remove_where(|c| c.get_attr("remove").is_some());
+        assert_eq!(
+            vec![XmbFileEntry::new("child2").attr("remove", "1")],
+            removed
+        );
+
+        assert_eq!(
+            vec!["child0", "child4", "child1"],
+            entry
+                .children
+                .iter()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert!(entry.child_by_name("child3").is_none());
+    }
+
+    #[test]
+    fn xmb_macro() {
+        let xmb_file = xmb! {
+            root(a = "1", b = "2") {
+                child1(id = "id2", b = "4") {
+                    subchild1(c = "7", d = "8", e = String::from("f"));
+                }
+                child2;
+                "ui:child3"(type = "5",) {}
+            }
+        };
+
+        assert_eq!(
+            XmbFile {
+                entries: vec![XmbFileEntry {
+                    name: "root".into(),
+                    attributes: indexmap!["a".into() => "1".into(), "b".into() => "2".into()],
+                    children: vec![
+                        XmbFileEntry {
+                            name: "child1".into(),
+                            attributes: indexmap!["id".into() => "id2".into(), "b".into() => "4".into()],
+                            children: vec![XmbFileEntry {
+                                name: "subchild1".into(),
+                                attributes: indexmap![
+                                    "c".into() => "7".into(),
+                                    "d".into() => "8".into(),
+                                    "e".into() => "f".into()
+                                ],
+                                children: Vec::new(),
+                            }],
+                        },
+                        XmbFileEntry {
+                            name: "child2".into(),
+                            attributes: IndexMap::new(),
+                            children: Vec::new(),
+                        },
+                        XmbFileEntry {
+                            name: "ui:child3".into(),
+                            attributes: indexmap!["type".into() => "5".into()],
+                            children: Vec::new(),
+                        },
+                    ],
+                }],
+            },
+            xmb_file
+        );
+    }
+
+    #[test]
+    fn compile_xml_matches_from_xml() {
+        let data = indoc! {r#"
+            <?xml version="1.0" encoding="UTF-8"?>
+            <!-- comment -->
+            <root xmlns:ui="http://example.com/ui" a="1">
+                <child1 id="id2">text<!-- comment -->more text
+                    <ui:subchild1 c="7" ui:c="8"/>
+                    <![CDATA[data]]>
+                </child1>
+                <child2 id="id1" xmlns="http://example.com"/>
+            </root>"#
+        };
+
+        let element = parse_xml(data.as_bytes()).unwrap();
+        let (xmb_file, dropped) = XmbFile::from_xml(&element).unwrap();
+        let mut expected = std::io::Cursor::new(Vec::new());
+        Xmb::from(&xmb_file).write(&mut expected).unwrap();
+
+        let (xmb, new_dropped) = compile_xml(data.as_bytes(), &FromXmlOptions::default()).unwrap();
+        let mut actual = std::io::Cursor::new(Vec::new());
+        xmb.write(&mut actual).unwrap();
+
+        assert_eq!(expected.into_inner(), actual.into_inner());
+        assert_eq!(dropped, new_dropped);
+
+        let result = compile_xml(data.as_bytes(), &FromXmlOptions { strict: true });
+        assert!(matches!(
+            result,
+            Err(CompileXmlError::CreateXmb(CreateXmbError::UnsupportedContent(d))) if d == dropped
+        ));
+    }
+
+    #[test]
+    fn xmb_write_xml_matches_to_xml() {
+        let xmb_file = xmb! {
+            root(a = "1", "xmlns:ui" = "http://example.com/ui") {
+                child1(id = "id2") {
+                    "ui:subchild1"(c = "7", "&" = "<\">");
+                    subchild2;
+                }
+                child2(id = "id1", xmlns = "http://example.com") {
+                    subchild3;
+                }
+            }
+        };
+        let xmb = Xmb::from(&xmb_file);
+
+        let config = xmltree::EmitterConfig::new()
+            .perform_indent(true)
+            .indent_string("    ")
+            .pad_self_closing(false);
+        let mut expected = Vec::new();
+        XmbFile::try_from(&xmb)
+            .unwrap()
+            .to_xml()
+            .unwrap()
+            .write_with_config(&mut expected, config)
+            .unwrap();
+
+        let mut actual = Vec::new();
+        xmb.write_xml(&mut actual, &XmlOptions::default()).unwrap();
+
+        let expected = String::from_utf8(expected).unwrap();
+        assert_eq!(expected, String::from_utf8(actual).unwrap());
+        assert_eq!(
+            expected,
+            xmb_file.to_xml_string(&XmlOptions::default()).unwrap()
+        );
+
+        let result = Xmb::from(&XmbFile {
+            entries: Vec::new(),
+        })
+        .write_xml(Vec::new(), &XmlOptions::default());
+        assert!(matches!(
+            result,
+            Err(WriteXmlError::CreateXml(CreateXmlError::MissingRootElement))
+        ));
+    }
+
+    #[test]
+    fn xmb_file_xml_options() {
+        let xmb_file = xmb! {
+            root(a = "1") {
+                child1 {
+                    subchild1;
+                }
+            }
+        };
+
+        let options = XmlOptions {
+            indent: None,
+            empty_elements: EmptyElementStyle::PaddedSelfClosing,
+            declaration: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            r#"<root a="1"><child1><subchild1 /></child1></root>"#,
+            xmb_file.to_xml_string(&options).unwrap()
+        );
+
+        let options = XmlOptions {
+            indent: Some("\t".into()),
+            empty_elements: EmptyElementStyle::StartEnd,
+            declaration: true,
+            encoding: XmlEncoding::Utf8Lowercase,
+        };
+        let text = xmb_file.to_xml_string(&options).unwrap();
+        assert_eq!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<root a=\"1\">\n\t<child1>\n\t\t<subchild1></subchild1>\n\t</child1>\n</root>",
+            text
+        );
+
+        let (new_xmb_file, dropped) =
+            XmbFile::from_xml_str(&text, &FromXmlOptions::default()).unwrap();
+        assert_eq!(xmb_file, new_xmb_file);
+        assert!(dropped.is_empty());
+
+        assert!(matches!(
+            XmbFile::from_xml_str("<root>", &FromXmlOptions::default()),
+            Err(ReadXmlError::Xml(_))
+        ));
+    }
+
+    #[test]
+    fn xmb_file_to_xmb_collation() {
+        let xmb_file = xmb! {
+            root {
+                a(id = "b");
+                "B"(id = "A");
+                aa(id = "aa");
+                "\u{e9}"(id = "\u{e9}");
+            }
+        };
+
+        let to_xmb = |collation| {
+            xmb_file.to_xmb(&BuildOptions {
+                collation,
+                ..Default::default()
+            })
+        };
+        let names = |xmb: &Xmb| {
+            xmb.string_offsets
+                .iter()
+                .map(|o| xmb.read_name(*o).unwrap())
+                .collect::<Vec<_>>()
+        };
+        let ids = |xmb: &Xmb| {
+            xmb.mapped_entries
+                .iter()
+                .map(|e| xmb.read_value(e.value_offset).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let xmb = to_xmb(Collation::Bytes);
+        assert_eq!(vec!["B", "a", "aa", "id", "root", "\u{e9}"], names(&xmb));
+        assert_eq!(vec!["A", "aa", "b", "\u{e9}"], ids(&xmb));
+        assert!(matches!(
+            xmb.detect_collations().unwrap().as_slice(),
+            [Collation::Bytes]
+        ));
+
+        let xmb = to_xmb(Collation::SignedBytes);
+        assert_eq!(vec!["\u{e9}", "B", "a", "aa", "id", "root"], names(&xmb));
+        assert_eq!(vec!["\u{e9}", "A", "aa", "b"], ids(&xmb));
+        assert!(matches!(
+            xmb.detect_collations().unwrap().as_slice(),
+            [Collation::SignedBytes]
+        ));
+
+        let xmb = to_xmb(Collation::CaseInsensitive);
+        assert_eq!(vec!["a", "aa", "B", "id", "root", "\u{e9}"], names(&xmb));
+        assert_eq!(vec!["A", "aa", "b", "\u{e9}"], ids(&xmb));
+        assert!(matches!(
+            xmb.detect_collations().unwrap().as_slice(),
+            [Collation::CaseInsensitive]
+        ));
+
+        let xmb = to_xmb(Collation::LengthFirst);
+        assert_eq!(vec!["B", "a", "aa", "id", "\u{e9}", "root"], names(&xmb));
+        assert_eq!(vec!["A", "b", "aa", "\u{e9}"], ids(&xmb));
+        assert!(matches!(
+            xmb.detect_collations().unwrap().as_slice(),
+            [Collation::LengthFirst]
+        ));
+
+        let xmb = to_xmb(Collation::Custom(|a, b| b.cmp(a)));
+        assert_eq!(vec!["\u{e9}", "root", "id", "aa", "a", "B"], names(&xmb));
+        assert!(xmb.detect_collations().unwrap().is_empty());
+    }
+
+    #[test]
+    fn xmb_file_to_xmb_string_layout() {
+        let xmb_file = xmb! {
+            root(type = "effect_main", name = "main") {
+                draw(type = "main", id = "in");
+                shadow(id = "effect_main", caster = "");
+            }
+        };
+        let to_xmb = |string_layout| {
+            xmb_file.to_xmb(&BuildOptions {
+                string_layout,
+                ..Default::default()
+            })
+        };
+
+        let xmb = to_xmb(StringLayout::FirstAppearance);
+        assert_eq!(
+            b"root\0type\0name\0draw\0id\0shadow\0caster\0",
+            &xmb.string_names.data[..]
+        );
+        assert_eq!(b"effect_main\0main\0in\0\0", &xmb.string_values.data[..]);
+
+        let sorted = to_xmb(StringLayout::Sorted);
+        assert_eq!(
+            b"caster\0draw\0id\0name\0root\0shadow\0type\0",
+            &sorted.string_names.data[..]
+        );
+        assert_eq!(b"\0effect_main\0in\0main\0", &sorted.string_values.data[..]);
+        assert_eq!(xmb_file, XmbFile::try_from(&sorted).unwrap());
+
+        // Check that alignment padding from reading a file is ignored.
+        let mut writer = std::io::Cursor::new(Vec::new());
+        sorted.write(&mut writer).unwrap();
+        writer.set_position(0);
+        let sorted_from_file = Xmb::read(&mut writer).unwrap();
+        let xmb = to_xmb(StringLayout::original(&sorted_from_file));
+        assert_eq!(sorted.string_names.data, xmb.string_names.data);
+        assert_eq!(sorted.string_values.data, xmb.string_values.data);
+
+        let xmb = to_xmb(StringLayout::SuffixMerged);
+        assert_eq!(b"effect_main\0", &xmb.string_values.data[..]);
+        assert_eq!(xmb_file, XmbFile::try_from(&xmb).unwrap());
+    }
+
+    #[test]
+    fn xmb_layout() {
+        let xmb_file = xmb! {
+            root(id = "a") {
+                child(id = "bc", value = "d");
+            }
+        };
+        let xmb = Xmb::from(&xmb_file);
+
+        let section = |offset, size| layout::Section { offset, size };
+        let expected = layout::XmbLayout {
+            header: section(0, 64),
+            string_offsets: section(64, 16),
+            entries: section(80, 32),
+            attributes: section(112, 24),
+            mapped_entries: section(136, 16),
+            string_names: section(152, 20),
+            string_values: section(172, 7),
+            file_size: 180,
+        };
+        assert_eq!(expected, xmb.layout().unwrap());
+
+        let mut writer = std::io::Cursor::new(Vec::new());
+        xmb.write(&mut writer).unwrap();
+        assert_eq!(180, writer.get_ref().len());
+
+        // The values extend to the end of the file when reading.
+        writer.set_position(0);
+        let layout = layout::XmbLayout::read(&mut writer).unwrap();
+        assert_eq!(
+            layout::XmbLayout {
+                string_values: section(172, 8),
+                ..expected
+            },
+            layout
+        );
+
+        writer.set_position(0);
+        assert_eq!(layout, Xmb::read(&mut writer).unwrap().layout().unwrap());
+    }
+
+    #[test]
+    fn xmb_file_index() {
+        let xmb_file = XmbFile {
+            entries: vec![XmbFileEntry::new("effect")
+                .child(
+                    XmbFileEntry::new("emitter")
+                        .attr("id", "smoke")
+                        .child(XmbFileEntry::new("link").attr("target", "flash")),
+                )
+                .child(XmbFileEntry::new("emitter").attr("id", "flash"))
+                .child(
+                    XmbFileEntry::new("group")
+                        .attr("first", "smoke")
+                        .attr("second", "flash"),
+                )],
+        };
+
+        let index = xmb_file.index();
+        assert_eq!(5, index.entries().len());
+
+        let flash = index.get_by_id("flash").unwrap();
+        assert_eq!(vec![0, 1], flash.path);
+        assert_eq!(Some(flash.entry), xmb_file.entry_at_path(&flash.path));
+        assert!(index.get_by_id("missing").is_none());
+
+        // Ids should use the same entries as the mapped entries.
+        let xmb = Xmb::from(&xmb_file);
+        for mapped in &xmb.mapped_entries {
+            let entry = &index.entries()[mapped.entry_index as usize];
+            let id = entry.entry.get_attr("id").unwrap();
+            assert_eq!(Some(entry), index.get_by_id(id));
+        }
+
+        let emitters: Vec<_> = index.entries_by_name("emitter").map(|e| &e.path).collect();
+        assert_eq!(vec![&vec![0, 0], &vec![0, 1]], emitters);
+        assert_eq!(0, index.entries_by_name("missing").count());
+
+        let references: Vec<_> = index
+            .references_to("flash")
+            .map(|r| (r.entry.path.as_slice(), r.attribute))
+            .collect();
+        assert_eq!(
+            vec![(&[0, 2][..], "second"), (&[0, 0, 0][..], "target")],
+            references
+        );
+        assert!(xmb_file.entry_at_path(&[0, 3]).is_none());
+        assert!(xmb_file.entry_at_path(&[]).is_none());
+    }
+
+    #[test]
+    fn xmb_file_iter_flattened() {
+        let xmb_file = XmbFile {
+            entries: vec![
+                XmbFileEntry::new("a")
+                    .child(
+                        XmbFileEntry::new("b")
+                            .child(XmbFileEntry::new("d"))
+                            .child(XmbFileEntry::new("e").child(XmbFileEntry::new("g"))),
+                    )
+                    .child(XmbFileEntry::new("c").child(XmbFileEntry::new("f"))),
+                XmbFileEntry::new("h").child(XmbFileEntry::new("i")),
+            ],
+        };
+
+        // The flattened order should match the order of the binary entries.
+        let xmb = Xmb::from(&xmb_file);
+        let entries: Vec<_> = xmb_file.iter_flattened().collect();
+        assert_eq!(xmb.entries.len(), entries.len());
+        for (i, (e, entry)) in xmb.entries.iter().zip(&entries).enumerate() {
+            let name = events::read_string_bytes(&xmb.string_names.data, e.name_offset).unwrap();
+            assert_eq!(entry.entry.name.as_bytes(), name);
+            assert_eq!(i, entry.index);
+            assert_eq!(entry.path.len() - 1, entry.depth);
+            assert_eq!(
+                e.parent_index,
+                entries
+                    .iter()
+                    .find(|p| p.path == entry.path[..entry.depth])
+                    .map(|p| p.index as i16)
+                    .unwrap_or(-1)
+            );
+        }
+
+        let flattened: Vec<_> = entries.iter().map(|e| e.entry.name.as_str()).collect();
+        assert_eq!(vec!["a", "h", "b", "c", "d", "e", "g", "f", "i"], flattened);
+
+        let document: Vec<_> = xmb_file
+            .iter_document_order()
+            .map(|e| (e.entry.name.as_str(), e.depth, e.index))
+            .collect();
+        assert_eq!(
+            vec![
+                ("a", 0, 0),
+                ("b", 1, 2),
+                ("d", 2, 4),
+                ("e", 2, 5),
+                ("g", 3, 6),
+                ("c", 1, 3),
+                ("f", 2, 7),
+                ("h", 0, 1),
+                ("i", 1, 8)
+            ],
+            document
+        );
+        assert_eq!(
+            Some(entries[6].entry),
+            xmb_file.entry_at_path(&entries[6].path)
+        );
+    }
+
+    #[test]
+    fn xmb_document_edit() {
+        let xmb_file = XmbFile {
+            entries: vec![XmbFileEntry::new("a")
+                .attr("id", "a")
+                .child(XmbFileEntry::new("b").child(XmbFileEntry::new("d")))
+                .child(XmbFileEntry::new("c"))],
+        };
+
+        // Ids should match the binary entry indices.
+        let mut document = XmbDocument::from(&xmb_file);
+        let xmb = Xmb::from(&document);
+        for entry in xmb_file.iter_flattened() {
+            let node = document.node(NodeId::from_index(entry.index)).unwrap();
+            assert_eq!(entry.entry.name, node.name);
+            assert_eq!(
+                xmb.entries[entry.index].parent_index,
+                node.parent().map(|p| p.index() as i16).unwrap_or(-1)
+            );
+        }
+        assert_eq!(xmb_file, document.to_xmb_file());
+        assert_eq!(xmb_file, XmbDocument::try_from(&xmb).unwrap().to_xmb_file());
+
+        let ids: Vec<_> = document
+            .children(document.roots().next().unwrap())
+            .collect();
+        let [b, c] = ids[..] else { panic!() };
+        let d = document.node(b).unwrap().first_child().unwrap();
+        assert_eq!(Some(c), document.node(b).unwrap().next_sibling());
+        assert_eq!(Some(b), document.node(c).unwrap().previous_sibling());
+
+        document.move_to(c, Some(d)).unwrap();
+        document.move_before(d, b).unwrap();
+        document
+            .node_mut(d)
+            .unwrap()
+            .attributes
+            .insert("x".to_string(), "1".to_string());
+        assert_eq!(
+            XmbFile {
+                entries: vec![XmbFileEntry::new("a")
+                    .attr("id", "a")
+                    .child(
+                        XmbFileEntry::new("d")
+                            .attr("x", "1")
+                            .child(XmbFileEntry::new("c"))
+                    )
+                    .child(XmbFileEntry::new("b"))],
+            },
+            document.to_xmb_file()
+        );
+
+        assert!(matches!(
+            document.move_to(d, Some(c)),
+            Err(DocumentError::MoveIntoDescendant { .. })
+        ));
+        assert!(matches!(
+            document.move_before(d, d),
+            Err(DocumentError::MoveIntoDescendant { .. })
+        ));
+
+        document.move_to(b, None).unwrap();
+        assert_eq!(2, document.roots().count());
+
+        let removed = document.remove(d).unwrap();
+        assert_eq!("d", removed.name);
+        assert!(document.node(c).is_none());
+        assert!(matches!(
+            document.append_child(c, "e"),
+            Err(DocumentError::NodeNotFound(_))
+        ));
+        let e = document.append_child(b, "e").unwrap();
+        assert_eq!(Some(b), document.node(e).unwrap().parent());
+        assert_eq!(
+            XmbFile {
+                entries: vec![
+                    XmbFileEntry::new("a").attr("id", "a"),
+                    XmbFileEntry::new("b").child(XmbFileEntry::new("e"))
+                ],
+            },
+            document.to_xmb_file()
+        );
+    }
+
+    #[test]
+    fn xmb_file_from_owned() {
+        let xmb_file = XmbFile {
+            entries: vec![XmbFileEntry::new("a")
+                .attr("id", "x")
+                .child(
+                    XmbFileEntry::new("b")
+                        .attr("id", "y")
+                        .child(XmbFileEntry::new("a")),
+                )
+                .child(XmbFileEntry::new("c").attr("id", "x"))],
+        };
+
+        let mut expected = std::io::Cursor::new(Vec::new());
+        Xmb::from(&xmb_file).write(&mut expected).unwrap();
+
+        let xmb = Xmb::from_owned(xmb_file.clone());
+        let mut writer = std::io::Cursor::new(Vec::new());
+        xmb.write(&mut writer).unwrap();
+        assert_eq!(expected.into_inner(), writer.into_inner());
+
+        assert_eq!(xmb_file, XmbFile::try_from(&xmb).unwrap());
+    }
+
+    #[test]
+    fn xmb_to_xmb_file_invalid_parent() {
+        let mut xmb = Xmb::from(&XmbFile {
+            entries: vec![XmbFileEntry::new("a")
+                .child(XmbFileEntry::new("b"))
+                .child(XmbFileEntry::new("c"))],
+        });
+
+        // Entries with invalid parents aren't reachable from the root.
+        xmb.entries[2].parent_index = 5;
+        assert_eq!(
+            XmbFile {
+                entries: vec![XmbFileEntry::new("a").child(XmbFileEntry::new("b"))]
+            },
+            XmbFile::try_from(&xmb).unwrap()
+        );
+    }
+
+    #[test]
+    fn xmb_verify_unk1() {
+        let mut xmb = Xmb::from(&XmbFile {
+            entries: vec![XmbFileEntry::new("a")
+                .child(XmbFileEntry::new("b").child(XmbFileEntry::new("d")))
+                .child(XmbFileEntry::new("c"))],
+        });
+        assert!(xmb.verify_unk1().is_empty());
+
+        xmb.entries[3].unk1 = -1;
+        let mismatches = xmb.verify_unk1();
+        assert_eq!(
+            vec![verify::Unk1Mismatch {
+                index: 3,
+                name: Some("d".to_string()),
+                path: Some(vec![0, 0, 0]),
+                actual: -1,
+                expected: 4
+            }],
+            mismatches
+        );
+        assert_eq!(
+            "entry 3 \"d\" at [0, 0, 0] has unk1 -1 instead of 4",
+            mismatches[0].to_string()
+        );
+
+        // Entries with invalid parents have no path.
+        xmb.entries[3].parent_index = 7;
+        let mismatches = xmb.verify_unk1();
+        assert_eq!(None, mismatches.iter().find(|m| m.index == 3).unwrap().path);
+    }
+
+    #[test]
+    fn xmb_repair() {
+        let xmb_file = XmbFile {
+            entries: vec![XmbFileEntry::new("a")
+                .child(XmbFileEntry::new("b").attr("id", "y"))
+                .child(XmbFileEntry::new("c").attr("id", "x"))],
+        };
+        let mut xmb = Xmb::from(&xmb_file);
+        let mut expected = std::io::Cursor::new(Vec::new());
+        xmb.write(&mut expected).unwrap();
+        assert!(xmb.repair().is_empty());
+
+        xmb.entry_count = 1;
+        xmb.entries[0].child_count = 0;
+        xmb.entries[2].unk1 = 0;
+        xmb.string_offsets.pop();
+        xmb.mapped_entries.swap(0, 1);
+
+        assert_eq!(
+            vec![
+                repair::Repair::ChildCount {
+                    index: 0,
+                    old: 0,
+                    new: 2
+                },
+                repair::Repair::Unk1 {
+                    index: 2,
+                    old: 0,
+                    new: 3
+                },
+                repair::Repair::StringOffsets,
+                repair::Repair::MappedEntries,
+                repair::Repair::Count {
+                    field: "entry_count",
+                    old: 1,
+                    new: 3
+                },
+            ],
+            xmb.repair()
+        );
+
+        let mut writer = std::io::Cursor::new(Vec::new());
+        xmb.write(&mut writer).unwrap();
+        assert_eq!(expected.into_inner(), writer.into_inner());
+    }
+
+    #[test]
+    fn xmb_edit_in_place() {
+        let mut xmb = Xmb::from(&XmbFile {
+            entries: vec![XmbFileEntry::new("a")
+                .child(XmbFileEntry::new("b").attr("id", "y").attr("x", "1"))
+                .child(XmbFileEntry::new("c").attr("id", "z"))],
+        });
+        let entries_before: Vec<_> = xmb.entries.iter().map(|e| format!("{e:?}")).collect();
+
+        // Only the value offset should change.
+        xmb.set_attribute_value(1, "x", "2").unwrap();
+        assert_eq!(
+            entries_before,
+            xmb.entries
+                .iter()
+                .map(|e| format!("{e:?}"))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(b"y\0\x31\0z\0\x32\0", &xmb.string_values.data[..]);
+
+        // Changing an id should keep the lookup sorted.
+        xmb.set_attribute_value(2, "id", "a").unwrap();
+        let ids: Vec<_> = xmb
+            .mapped_entries
+            .iter()
+            .map(|e| (xmb.read_value(e.value_offset).unwrap(), e.entry_index))
+            .collect();
+        assert_eq!(vec![("a".to_string(), 2), ("y".to_string(), 1)], ids);
+
+        xmb.rename_entry(0, "root").unwrap();
+        xmb.add_attribute(0, "id", "r").unwrap();
+        xmb.add_attribute(2, "w", "1").unwrap();
+
+        assert_eq!(
+            XmbFile {
+                entries: vec![XmbFileEntry::new("root")
+                    .attr("id", "r")
+                    .child(XmbFileEntry::new("b").attr("id", "y").attr("x", "2"))
+                    .child(XmbFileEntry::new("c").attr("id", "a").attr("w", "1"))],
+            },
+            XmbFile::try_from(&xmb).unwrap()
+        );
+        assert_eq!(1, xmb.entries[1].attribute_start_index);
+        assert_eq!(3, xmb.entries[2].attribute_start_index);
+        assert_eq!(3, xmb.mapped_entry_count);
+
+        // All derived values should already be consistent.
+        assert!(xmb.repair().is_empty());
+
+        assert!(matches!(
+            xmb.set_attribute_value(1, "missing", ""),
+            Err(edit::EditXmbError::MissingAttribute { entry: 1, .. })
+        ));
+        assert!(matches!(
+            xmb.add_attribute(1, "x", ""),
+            Err(edit::EditXmbError::DuplicateAttribute { entry: 1, .. })
+        ));
+        assert!(matches!(
+            xmb.rename_entry(3, "d"),
+            Err(edit::EditXmbError::InvalidEntry(3))
+        ));
+        assert!(matches!(
+            xmb.rename_entry(0, "\0"),
+            Err(edit::EditXmbError::NullByte(_))
+        ));
+    }
+
+    #[test]
+    fn xmb_events() {
+        let xmb_file = xmb! {
+            root(a = "1") {
+                child1(id = "id2") {
+                    subchild1(c = "7", d = "8");
+                }
+                child2;
+            }
+        };
+        let mut xmb = Xmb::from(&xmb_file);
+
+        // Entries are stored with all the children of an entry first.
+        let events: Vec<_> = xmb.events().collect::<Result<_, _>>().unwrap();
+        assert_eq!(
+            vec![
+                XmbEvent::StartEntry {
+                    index: 0,
+                    name: "root".into(),
+                    attributes: vec![("a".into(), "1".into())]
+                },
+                XmbEvent::StartEntry {
+                    index: 1,
+                    name: "child1".into(),
+                    attributes: vec![("id".into(), "id2".into())]
+                },
+                XmbEvent::StartEntry {
+                    index: 3,
+                    name: "subchild1".into(),
+                    attributes: vec![("c".into(), "7".into()), ("d".into(), "8".into())]
+                },
+                XmbEvent::EndEntry,
+                XmbEvent::EndEntry,
+                XmbEvent::StartEntry {
+                    index: 2,
+                    name: "child2".into(),
+                    attributes: Vec::new()
+                },
+                XmbEvent::EndEntry,
+                XmbEvent::EndEntry,
+            ],
+            events
+        );
+
+        xmb.entries[2].name_offset = xmb.string_names.data.len() as u32;
+        let mut events = xmb.events();
+        assert_eq!(5, events.by_ref().take_while(|e| e.is_ok()).count());
+        assert!(events.next().is_none());
+        assert!(matches!(
+            xmb.events().nth(5),
+            Some(Err(ReadXmbError::InvalidEntry(2)))
+        ));
+    }
+
     // TODO: Test xmb to xml_file
     #[test]
     fn xmb_file_to_xmb() {
@@ -0,0 +1,96 @@
+/// Create an [XmbFile](crate::XmbFile) using XML-like syntax.
+///
+/// Each entry is a name followed by optional attributes in parentheses.
+/// Entries end with `;` or a block of child entries in braces.
+/// Names can be identifiers or string literals for names that aren't valid identifiers.
+/// Attribute values can be any expression that converts into a [String].
+/// # Examples
+/// The example below creates the entries for the following XML document.
+/**
+```xml
+<model type="effect_main">
+    <shadow caster="0"/>
+    <draw>
+        <draw buffer="0" type="main"/>
+    </draw>
+    <ui:draw xmlns:ui="http://example.com/ui"/>
+</model>
+```
+
+```rust
+use xmb_lib::xmb;
+
+let xmb_file = xmb! {
+    model(type = "effect_main") {
+        shadow(caster = "0");
+        draw {
+            draw(buffer = "0", type = "main");
+        }
+        "ui:draw"("xmlns:ui" = "http://example.com/ui");
+    }
+};
+assert_eq!("effect_main", xmb_file.entries[0].attributes["type"]);
+```
+ */
+#[macro_export]
+macro_rules! xmb {
+    ($($entries:tt)*) => {
+        $crate::XmbFile {
+            entries: $crate::__xmb_entries!([] $($entries)*),
+        }
+    };
+}
+
+// Accumulate entries one at a time since each entry can have a different form.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __xmb_entries {
+    ([$($entry:expr,)*]) => {
+        ::std::vec![$($entry),*]
+    };
+    ([$($entry:expr,)*] $name:tt ($($attributes:tt)*) ; $($rest:tt)*) => {
+        $crate::__xmb_entries!(
+            [$($entry,)* $crate::__xmb_entry!($name ($($attributes)*) {}),]
+            $($rest)*
+        )
+    };
+    ([$($entry:expr,)*] $name:tt ($($attributes:tt)*) { $($children:tt)* } $($rest:tt)*) => {
+        $crate::__xmb_entries!(
+            [$($entry,)* $crate::__xmb_entry!($name ($($attributes)*) { $($children)* }),]
+            $($rest)*
+        )
+    };
+    ([$($entry:expr,)*] $name:tt ; $($rest:tt)*) => {
+        $crate::__xmb_entries!(
+            [$($entry,)* $crate::__xmb_entry!($name () {}),]
+            $($rest)*
+        )
+    };
+    ([$($entry:expr,)*] $name:tt { $($children:tt)* } $($rest:tt)*) => {
+        $crate::__xmb_entries!(
+            [$($entry,)* $crate::__xmb_entry!($name () { $($children)* }),]
+            $($rest)*
+        )
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __xmb_entry {
+    ($name:tt ($($key:tt = $value:expr),* $(,)?) { $($children:tt)* }) => {
+        $crate::XmbFileEntry::new($crate::__xmb_name!($name))
+            $(.attr($crate::__xmb_name!($key), $value))*
+            .children($crate::__xmb_entries!([] $($children)*))
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __xmb_name {
+    ($name:ident) => {
+        ::std::stringify!($name)
+    };
+    ($name:literal) => {
+        $name
+    };
+}
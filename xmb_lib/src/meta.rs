@@ -0,0 +1,379 @@
+//! Sidecar metadata for XML content that can't be stored in an XMB file.
+//!
+//! XMB only stores elements and attributes, so comments and processing instructions
+//! are lost when compiling XML. An [XmbMeta] records these nodes along with their position
+//! in the tree so they can be merged back in with [XmbFile::to_xml_with_meta](crate::XmbFile::to_xml_with_meta).
+//!
+//! The metadata is stored as a separate XML document, usually next to the XMB like `model.xmb.meta`.
+/*!
+```xml
+<?xml version="1.0" encoding="UTF-8"?>
+<xmb_meta>
+    <comment path="0/1" index="0" text=" main draw passes "/>
+</xmb_meta>
+```
+ */
+use std::io::{Read, Write};
+use std::path::Path;
+use thiserror::Error;
+use xmltree::{Element, EmitterConfig, XMLNode};
+
+#[derive(Debug, Error)]
+pub enum ReadMetaError {
+    #[error("failed to parse metadata XML")]
+    Xml(#[from] xmltree::ParseError),
+
+    #[error("expected root element xmb_meta but found {0}")]
+    InvalidRootElement(String),
+
+    #[error("missing attribute {attribute} for {element}")]
+    MissingAttribute {
+        element: String,
+        attribute: &'static str,
+    },
+
+    #[error("invalid entry path {0:?}")]
+    InvalidPath(String),
+
+    #[error("invalid child index {0:?}")]
+    InvalidIndex(String),
+
+    #[error("unsupported metadata element {0}")]
+    UnsupportedElement(String),
+
+    #[error("failed to read metadata")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum WriteMetaError {
+    #[error("failed to write metadata XML")]
+    Xml(#[from] xmltree::Error),
+
+    #[error("failed to write metadata")]
+    Io(#[from] std::io::Error),
+}
+
+/// XML nodes from the original document that aren't representable in XMB.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct XmbMeta {
+    /// The nodes in document order.
+    pub nodes: Vec<MetaNode>,
+}
+
+/// A comment or processing instruction anchored to a position in the entry tree.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MetaNode {
+    /// The indices of each [XmbFileEntry](crate::XmbFileEntry) starting from [entries](crate::XmbFile::entries).
+    /// The root element has a path of `[0]`.
+    pub path: Vec<usize>,
+    /// The number of child elements of the entry at [path](#structfield.path) that precede this node.
+    pub index: usize,
+    pub kind: MetaNodeKind,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum MetaNodeKind {
+    Comment(String),
+    ProcessingInstruction(String, Option<String>),
+}
+
+impl XmbMeta {
+    /// Collect the comments and processing instructions from the elements of `root`.
+    /// Nodes outside the root element are not included.
+    pub fn from_xml(root: &Element) -> Self {
+        let mut nodes = Vec::new();
+        add_meta_nodes_recursive(root, &mut vec![0], &mut nodes);
+        Self { nodes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn read<R: Read>(reader: R) -> Result<Self, ReadMetaError> {
+        let root = Element::parse(reader)?;
+        if root.name != "xmb_meta" {
+            return Err(ReadMetaError::InvalidRootElement(root.name));
+        }
+
+        let nodes = root
+            .children
+            .iter()
+            .filter_map(XMLNode::as_element)
+            .map(read_meta_node)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { nodes })
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ReadMetaError> {
+        let data = std::fs::read(path)?;
+        Self::read(std::io::Cursor::new(data))
+    }
+
+    pub fn write<W: Write>(&self, writer: W) -> Result<(), WriteMetaError> {
+        let mut root = Element::new("xmb_meta");
+        root.children = self
+            .nodes
+            .iter()
+            .map(|node| XMLNode::Element(create_meta_element(node)))
+            .collect();
+
+        let config = EmitterConfig::new()
+            .perform_indent(true)
+            .indent_string("    ")
+            .pad_self_closing(false);
+        root.write_with_config(writer, config)?;
+        Ok(())
+    }
+
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), WriteMetaError> {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+fn add_meta_nodes_recursive(element: &Element, path: &mut Vec<usize>, nodes: &mut Vec<MetaNode>) {
+    let mut index = 0;
+    for child in &element.children {
+        let kind = match child {
+            XMLNode::Element(e) => {
+                path.push(index);
+                add_meta_nodes_recursive(e, path, nodes);
+                path.pop();
+
+                index += 1;
+                continue;
+            }
+            XMLNode::Comment(text) => MetaNodeKind::Comment(text.clone()),
+            XMLNode::ProcessingInstruction(name, data) => {
+                MetaNodeKind::ProcessingInstruction(name.clone(), data.clone())
+            }
+            XMLNode::CData(_) | XMLNode::Text(_) => continue,
+        };
+
+        nodes.push(MetaNode {
+            path: path.clone(),
+            index,
+            kind,
+        });
+    }
+}
+
+/// Insert the nodes from `meta` into `root` and its descendants.
+/// The children of each element should initially contain only elements.
+pub(crate) fn merge_meta(root: &mut Element, meta: &XmbMeta) {
+    // Sort by position to find the nodes for each element with a binary search.
+    // The sort is stable to preserve the order of nodes at the same position.
+    let mut nodes: Vec<_> = meta.nodes.iter().collect();
+    nodes.sort_by(|a, b| (&a.path, a.index).cmp(&(&b.path, b.index)));
+    merge_meta_recursive(root, &mut vec![0], &nodes);
+}
+
+fn merge_meta_recursive(element: &mut Element, path: &mut Vec<usize>, nodes: &[&MetaNode]) {
+    for (i, child) in element.children.iter_mut().enumerate() {
+        if let XMLNode::Element(child) = child {
+            path.push(i);
+            merge_meta_recursive(child, path, nodes);
+            path.pop();
+        }
+    }
+
+    let start = nodes.partition_point(|n| n.path < *path);
+    let len = nodes[start..].partition_point(|n| n.path == *path);
+    merge_meta_nodes(element, &nodes[start..start + len]);
+}
+
+// Nodes with a child index past the end are added as the last children.
+fn merge_meta_nodes(element: &mut Element, nodes: &[&MetaNode]) {
+    if nodes.is_empty() {
+        return;
+    }
+
+    let mut nodes = nodes.iter().peekable();
+    let element_children = std::mem::take(&mut element.children);
+    for (i, child) in element_children.into_iter().enumerate() {
+        while let Some(node) = nodes.next_if(|n| n.index <= i) {
+            element.children.push(create_xml_node(&node.kind));
+        }
+        element.children.push(child);
+    }
+
+    // Remaining nodes come after all child elements.
+    for node in nodes {
+        element.children.push(create_xml_node(&node.kind));
+    }
+}
+
+fn create_xml_node(kind: &MetaNodeKind) -> XMLNode {
+    match kind {
+        MetaNodeKind::Comment(text) => XMLNode::Comment(text.clone()),
+        MetaNodeKind::ProcessingInstruction(name, data) => {
+            XMLNode::ProcessingInstruction(name.clone(), data.clone())
+        }
+    }
+}
+
+fn create_meta_element(node: &MetaNode) -> Element {
+    let path = node
+        .path
+        .iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let (name, attributes) = match &node.kind {
+        MetaNodeKind::Comment(text) => ("comment", vec![("text", Some(text))]),
+        MetaNodeKind::ProcessingInstruction(target, data) => (
+            "processing_instruction",
+            vec![("target", Some(target)), ("data", data.as_ref())],
+        ),
+    };
+
+    let mut element = Element::new(name);
+    element.attributes.insert("path".into(), path);
+    element
+        .attributes
+        .insert("index".into(), node.index.to_string());
+    for (k, v) in attributes {
+        if let Some(v) = v {
+            element.attributes.insert(k.into(), v.clone());
+        }
+    }
+    element
+}
+
+fn read_meta_node(element: &Element) -> Result<MetaNode, ReadMetaError> {
+    let attribute = |name: &'static str| {
+        element
+            .attributes
+            .get(name)
+            .ok_or_else(|| ReadMetaError::MissingAttribute {
+                element: element.name.clone(),
+                attribute: name,
+            })
+    };
+
+    let path_text = attribute("path")?;
+    let path = path_text
+        .split('/')
+        .map(|i| i.parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| ReadMetaError::InvalidPath(path_text.clone()))?;
+
+    let index_text = attribute("index")?;
+    let index = index_text
+        .parse()
+        .map_err(|_| ReadMetaError::InvalidIndex(index_text.clone()))?;
+
+    let kind = match element.name.as_str() {
+        "comment" => MetaNodeKind::Comment(attribute("text")?.clone()),
+        "processing_instruction" => MetaNodeKind::ProcessingInstruction(
+            attribute("target")?.clone(),
+            element.attributes.get("data").cloned(),
+        ),
+        _ => return Err(ReadMetaError::UnsupportedElement(element.name.clone())),
+    };
+
+    Ok(MetaNode { path, index, kind })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{XmbFile, XmbFileEntry};
+
+    fn comment(path: &[usize], index: usize, text: &str) -> MetaNode {
+        MetaNode {
+            path: path.to_vec(),
+            index,
+            kind: MetaNodeKind::Comment(text.to_string()),
+        }
+    }
+
+    fn xmb_file() -> XmbFile {
+        XmbFile {
+            entries: vec![XmbFileEntry::new("root")
+                .child(XmbFileEntry::new("a").child(XmbFileEntry::new("c")))
+                .child(XmbFileEntry::new("b"))],
+        }
+    }
+
+    #[test]
+    fn merge_out_of_order_nodes() {
+        let nodes = vec![
+            comment(&[0], 0, "1"),
+            comment(&[0], 0, "2"),
+            comment(&[0, 0], 1, "3"),
+            comment(&[0], 1, "4"),
+            comment(&[0], 5, "5"),
+        ];
+        let expected = xmb_file()
+            .to_xml_with_meta(&XmbMeta {
+                nodes: nodes.clone(),
+            })
+            .unwrap();
+
+        // Hand edited sidecar files may not list nodes in document order.
+        let reordered = XmbMeta {
+            nodes: vec![
+                nodes[4].clone(),
+                nodes[3].clone(),
+                nodes[0].clone(),
+                nodes[2].clone(),
+                nodes[1].clone(),
+            ],
+        };
+        let element = xmb_file().to_xml_with_meta(&reordered).unwrap();
+        assert_eq!(expected, element);
+
+        // Nodes at the same position keep their relative order.
+        // Indices past the last child are placed after the last child.
+        let mut expected_nodes = nodes;
+        expected_nodes[4].index = 2;
+        assert_eq!(expected_nodes, XmbMeta::from_xml(&element).nodes);
+    }
+
+    #[test]
+    fn merge_unmatched_paths() {
+        let meta = XmbMeta {
+            nodes: vec![comment(&[1], 0, "x"), comment(&[0, 5], 0, "y")],
+        };
+        assert_eq!(
+            xmb_file().to_xml().unwrap(),
+            xmb_file().to_xml_with_meta(&meta).unwrap()
+        );
+    }
+
+    #[test]
+    fn read_invalid_meta() {
+        let read = |text: &str| XmbMeta::read(text.as_bytes());
+        assert!(matches!(
+            read("<meta/>"),
+            Err(ReadMetaError::InvalidRootElement(name)) if name == "meta"
+        ));
+        assert!(matches!(
+            read(r#"<xmb_meta><comment index="0" text=""/></xmb_meta>"#),
+            Err(ReadMetaError::MissingAttribute {
+                attribute: "path",
+                ..
+            })
+        ));
+        assert!(matches!(
+            read(r#"<xmb_meta><comment path="0/a" index="0" text=""/></xmb_meta>"#),
+            Err(ReadMetaError::InvalidPath(_))
+        ));
+        assert!(matches!(
+            read(r#"<xmb_meta><comment path="0" index="-1" text=""/></xmb_meta>"#),
+            Err(ReadMetaError::InvalidIndex(_))
+        ));
+        assert!(matches!(
+            read(r#"<xmb_meta><cdata path="0" index="0"/></xmb_meta>"#),
+            Err(ReadMetaError::UnsupportedElement(_))
+        ));
+        assert!(read("<xmb_meta/>").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,564 @@
+//! Recalculating the values in an [Xmb] that are derived from the entries and strings.
+//!
+//! The counts in the header determine how much data [Xmb::read] reads for each section,
+//! so files with incorrect counts should be read with [Xmb::read_lenient] before calling [Xmb::repair].
+use crate::collation::Collation;
+use crate::events::read_string_bytes;
+use crate::index::ID_ATTRIBUTE;
+use crate::layout::{
+    Header, ALIGNMENT, ATTRIBUTE_SIZE, ENTRY_SIZE, HEADER_SIZE, MAPPED_ENTRY_SIZE,
+    STRING_OFFSET_SIZE,
+};
+use crate::xmb::{Attribute, Entry, MappedEntry, NamesBuffer, ValuesBuffer, Xmb};
+use crate::ReadXmbError;
+use binrw::{BinRead, BinReaderExt, VecArgs};
+use indexmap::IndexMap;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+/// A change made by [Xmb::repair].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Repair {
+    /// A count like [entry_count](crate::xmb::Xmb::entry_count) didn't match the length of its data.
+    Count {
+        field: &'static str,
+        old: u32,
+        new: u32,
+    },
+    /// The [child_count](crate::xmb::Entry::child_count) didn't match the number of children.
+    ChildCount { index: usize, old: u16, new: u16 },
+    /// The number of children is too large for [child_count](crate::xmb::Entry::child_count),
+    /// so the count was set to the maximum value.
+    ChildCountOverflow {
+        index: usize,
+        old: u16,
+        children: usize,
+    },
+    /// The [unk1](crate::xmb::Entry::unk1) didn't match the value calculated from the parent indices.
+    Unk1 { index: usize, old: i16, new: i16 },
+    /// The [string_offsets](crate::xmb::Xmb::string_offsets) were missing strings or weren't sorted.
+    StringOffsets,
+    /// The [mapped_entries](crate::xmb::Xmb::mapped_entries) were missing ids or weren't sorted.
+    MappedEntries,
+}
+
+impl std::fmt::Display for Repair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Repair::Count { field, old, new } => write!(f, "changed {field} from {old} to {new}"),
+            Repair::ChildCount { index, old, new } => write!(
+                f,
+                "changed child_count for entry {index} from {old} to {new}"
+            ),
+            Repair::ChildCountOverflow {
+                index,
+                old,
+                children,
+            } => write!(
+                f,
+                "changed child_count for entry {index} from {old} to {} since {children} children exceeds the maximum",
+                u16::MAX
+            ),
+            Repair::Unk1 { index, old, new } => {
+                write!(f, "changed unk1 for entry {index} from {old} to {new}")
+            }
+            Repair::StringOffsets => write!(f, "rebuilt string_offsets"),
+            Repair::MappedEntries => write!(f, "rebuilt mapped_entries"),
+        }
+    }
+}
+
+impl Xmb {
+    /// Read a file like [read](#method.read) but calculate the length of each section from the offsets in the header.
+    ///
+    /// The counts like [entry_count](#structfield.entry_count) are read but not used,
+    /// so [repair](#method.repair) can find and fix incorrect counts.
+    /// The names buffer is followed by padding, so trailing empty names are only kept
+    /// if they have an offset in [string_offsets](#structfield.string_offsets) or are used by an entry or attribute.
+    ///
+    /// Returns [ReadXmbError::InvalidSection] if the sections aren't in the expected order.
+    pub fn read_lenient<R: Read + Seek>(reader: &mut R) -> Result<Self, ReadXmbError> {
+        let start = reader.stream_position()?;
+        let header: Header = reader.read_le()?;
+
+        reader.seek(SeekFrom::Start(start))?;
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        // Each section ends at the start of the next section or the end of the file.
+        let offsets = [
+            header.string_offsets_offset,
+            header.entries_offset,
+            header.attributes_offset,
+            header.mapped_entries_offset,
+            header.string_names_offset,
+            header.string_values_offset,
+        ];
+        let section = |i: usize| {
+            let start = offsets[i];
+            let end = offsets.get(i + 1).copied().unwrap_or(bytes.len() as u32);
+            if start < HEADER_SIZE || start > end || end as usize > bytes.len() {
+                Err(ReadXmbError::InvalidSection(start))
+            } else {
+                Ok(&bytes[start as usize..end as usize])
+            }
+        };
+
+        let string_offsets: Vec<u32> = read_items(section(0)?, STRING_OFFSET_SIZE)?;
+        let entries: Vec<Entry> = read_items(section(1)?, ENTRY_SIZE)?;
+        let attributes: Vec<Attribute> = read_items(section(2)?, ATTRIBUTE_SIZE)?;
+
+        let name_offsets = string_offsets
+            .iter()
+            .copied()
+            .chain(entries.iter().map(|e| e.name_offset))
+            .chain(attributes.iter().map(|a| a.name_offset));
+        // The string offsets section has an entry for each name.
+        let string_count = string_offsets.len() as u32;
+        let names = names_data(section(4)?, string_count, name_offsets);
+
+        Ok(Self {
+            entry_count: header.entry_count,
+            attribute_count: header.attribute_count,
+            string_count: header.string_count,
+            mapped_entry_count: header.mapped_entry_count,
+            string_offsets,
+            entries,
+            attributes,
+            mapped_entries: read_items(section(3)?, MAPPED_ENTRY_SIZE)?,
+            string_names: NamesBuffer { data: names },
+            string_values: ValuesBuffer {
+                data: section(5)?.to_vec(),
+            },
+            unks: header.unks,
+        })
+    }
+
+    /// Recalculate the counts, child counts, [unk1](crate::xmb::Entry::unk1) values,
+    /// and lookup tables from the entries, attributes, and strings.
+    /// Returns the changes that were made or an empty list if the file was already consistent.
+    ///
+    /// The lookup tables are sorted with the first collation from [detect_collations](#method.detect_collations)
+    /// or the default [Collation] if the existing tables don't use a known ordering.
+    /// Strings with invalid offsets are not included in the lookup tables.
+    pub fn repair(&mut self) -> Vec<Repair> {
+        let mut repairs = Vec::new();
+
+        let collation = self
+            .detect_collations()
+            .ok()
+            .and_then(|c| c.first().copied())
+            .unwrap_or_default();
+
+        let child_counts = self.child_counts();
+        for (index, (entry, children)) in self.entries.iter_mut().zip(child_counts).enumerate() {
+            let old = entry.child_count;
+            match u16::try_from(children) {
+                Ok(new) if new != old => {
+                    repairs.push(Repair::ChildCount { index, old, new });
+                    entry.child_count = new;
+                }
+                Ok(_) => (),
+                Err(_) => {
+                    repairs.push(Repair::ChildCountOverflow {
+                        index,
+                        old,
+                        children,
+                    });
+                    entry.child_count = u16::MAX;
+                }
+            }
+        }
+
+        for mismatch in self.verify_unk1() {
+            self.entries[mismatch.index].unk1 = mismatch.expected;
+            repairs.push(Repair::Unk1 {
+                index: mismatch.index,
+                old: mismatch.actual,
+                new: mismatch.expected,
+            });
+        }
+
+        let string_offsets = self.sorted_string_offsets(collation);
+        if string_offsets != self.string_offsets {
+            self.string_offsets = string_offsets;
+            repairs.push(Repair::StringOffsets);
+        }
+
+        let mapped_entries = self.sorted_mapped_entries(collation);
+        let is_same = mapped_entries.len() == self.mapped_entries.len()
+            && mapped_entries
+                .iter()
+                .zip(&self.mapped_entries)
+                .all(|(a, b)| a.value_offset == b.value_offset && a.entry_index == b.entry_index);
+        if !is_same {
+            self.mapped_entries = mapped_entries;
+            repairs.push(Repair::MappedEntries);
+        }
+
+        let counts = [
+            ("entry_count", &mut self.entry_count, self.entries.len()),
+            (
+                "attribute_count",
+                &mut self.attribute_count,
+                self.attributes.len(),
+            ),
+            (
+                "string_count",
+                &mut self.string_count,
+                self.string_offsets.len(),
+            ),
+            (
+                "mapped_entry_count",
+                &mut self.mapped_entry_count,
+                self.mapped_entries.len(),
+            ),
+        ];
+        for (field, count, len) in counts {
+            let new = len as u32;
+            if *count != new {
+                repairs.push(Repair::Count {
+                    field,
+                    old: *count,
+                    new,
+                });
+                *count = new;
+            }
+        }
+
+        repairs
+    }
+
+    fn child_counts(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.entries.len()];
+        for entry in &self.entries {
+            if let Some(count) = usize::try_from(entry.parent_index)
+                .ok()
+                .and_then(|p| counts.get_mut(p))
+            {
+                *count += 1;
+            }
+        }
+        counts
+    }
+
+    // Offsets for all strings in the names buffer in sorted order.
+    fn sorted_string_offsets(&self, collation: Collation) -> Vec<u32> {
+        let data = &self.string_names.data;
+        let mut offsets = Vec::new();
+        let mut offset = 0;
+        while let Some(s) = read_string_bytes(data, offset) {
+            offsets.push(offset);
+            offset += s.len() as u32 + 1;
+        }
+
+        // Use a stable sort to preserve the order of duplicate strings.
+        offsets.sort_by(|a, b| {
+            collation.compare(
+                read_string_bytes(data, *a).unwrap_or_default(),
+                read_string_bytes(data, *b).unwrap_or_default(),
+            )
+        });
+        offsets
+    }
+
+    pub(crate) fn sorted_mapped_entries(&self, collation: Collation) -> Vec<MappedEntry> {
+        // The last entry with an id is used like when building an Xmb.
+        let mut entry_by_id = IndexMap::new();
+        for (i, entry) in self.entries.iter().enumerate() {
+            let id_offset = (0..entry.attribute_count).find_map(|a| {
+                let index = usize::try_from(entry.attribute_start_index).ok()? + a as usize;
+                let attribute = self.attributes.get(index)?;
+                let name = read_string_bytes(&self.string_names.data, attribute.name_offset)?;
+                (name == ID_ATTRIBUTE.as_bytes()).then_some(attribute.value_offset)
+            });
+
+            if let Some(offset) = id_offset {
+                if let Some(value) = read_string_bytes(&self.string_values.data, offset) {
+                    entry_by_id.insert(value, (offset, i));
+                }
+            }
+        }
+        entry_by_id.sort_by(|a, _, b, _| collation.compare(a, b));
+
+        entry_by_id
+            .values()
+            .map(|(value_offset, entry_index)| MappedEntry {
+                value_offset: *value_offset,
+                entry_index: *entry_index as u32,
+            })
+            .collect()
+    }
+}
+
+// Read as many items as fit in the section and ignore any padding.
+fn read_items<T>(bytes: &[u8], item_size: u32) -> Result<Vec<T>, ReadXmbError>
+where
+    T: for<'a> BinRead<Args<'a> = ()> + 'static,
+{
+    let count = bytes.len() / item_size as usize;
+    let args = VecArgs { count, inner: () };
+    Vec::read_le_args(&mut Cursor::new(bytes), args).map_err(Into::into)
+}
+
+fn names_data(bytes: &[u8], string_count: u32, offsets: impl Iterator<Item = u32>) -> Vec<u8> {
+    // Use the string count if the remaining bytes could be padding.
+    let counted_end = string_count
+        .checked_sub(1)
+        .and_then(|n| {
+            bytes
+                .iter()
+                .enumerate()
+                .filter(|(_, b)| **b == 0)
+                .nth(n as usize)
+        })
+        .map(|(i, _)| i + 1)
+        .filter(|end| {
+            bytes.len() - end < ALIGNMENT as usize && bytes[*end..].iter().all(|b| *b == 0)
+        });
+
+    // Otherwise, assume all trailing null bytes after the last terminator are padding.
+    let end = counted_end.unwrap_or_else(|| {
+        bytes
+            .iter()
+            .rposition(|b| *b != 0)
+            .map(|i| i + 2)
+            .unwrap_or_default()
+            .min(bytes.len())
+    });
+
+    // Include any empty strings that are still referenced.
+    let referenced_end = offsets
+        .filter_map(|o| read_string_bytes(bytes, o).map(|s| o as usize + s.len() + 1))
+        .max()
+        .unwrap_or_default();
+    bytes[..end.max(referenced_end)].to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{xmb, XmbFile, XmbFileEntry};
+
+    fn write_bytes(xmb: &Xmb) -> Vec<u8> {
+        let mut writer = Cursor::new(Vec::new());
+        xmb.write(&mut writer).unwrap();
+        writer.into_inner()
+    }
+
+    fn test_bytes() -> Vec<u8> {
+        write_bytes(&Xmb::from(&XmbFile {
+            entries: vec![XmbFileEntry::new("a")
+                .child(XmbFileEntry::new("b").attr("id", "y"))
+                .child(XmbFileEntry::new("c").attr("id", "x").attr("d", ""))],
+        }))
+    }
+
+    #[test]
+    fn read_lenient_incorrect_counts() {
+        let expected = test_bytes();
+
+        // Set entry_count, attribute_count, string_count, and mapped_entry_count.
+        let mut bytes = expected.clone();
+        for (i, count) in [1u32, 9, 0, 5].into_iter().enumerate() {
+            bytes[4 + i * 4..8 + i * 4].copy_from_slice(&count.to_le_bytes());
+        }
+        // The counts determine the section lengths for a normal read.
+        assert!(Xmb::read(&mut Cursor::new(&bytes)).is_err());
+
+        let mut xmb = Xmb::read_lenient(&mut Cursor::new(&bytes)).unwrap();
+        assert_eq!(
+            vec![
+                Repair::Count {
+                    field: "entry_count",
+                    old: 1,
+                    new: 3
+                },
+                Repair::Count {
+                    field: "attribute_count",
+                    old: 9,
+                    new: 3
+                },
+                Repair::Count {
+                    field: "string_count",
+                    old: 0,
+                    new: 5
+                },
+                Repair::Count {
+                    field: "mapped_entry_count",
+                    old: 5,
+                    new: 2
+                },
+            ],
+            xmb.repair()
+        );
+        assert_eq!(expected, write_bytes(&xmb));
+    }
+
+    #[test]
+    fn read_lenient_consistent_file() {
+        let expected = test_bytes();
+        let mut xmb = Xmb::read_lenient(&mut Cursor::new(&expected)).unwrap();
+        assert!(xmb.repair().is_empty());
+        assert_eq!(expected, write_bytes(&xmb));
+    }
+
+    #[test]
+    fn read_lenient_out_of_order_sections() {
+        let mut bytes = test_bytes();
+        // Move the entries offset past the attributes offset.
+        let attributes_offset = bytes[28..32].to_vec();
+        bytes[24..28].copy_from_slice(&attributes_offset);
+        bytes[24] += 1;
+        assert!(matches!(
+            Xmb::read_lenient(&mut Cursor::new(&bytes)),
+            Err(ReadXmbError::InvalidSection(_))
+        ));
+
+        let mut bytes = test_bytes();
+        bytes[20..24].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            Xmb::read_lenient(&mut Cursor::new(&bytes)),
+            Err(ReadXmbError::InvalidSection(u32::MAX))
+        ));
+    }
+
+    #[test]
+    fn names_data_padding() {
+        // The string count distinguishes trailing empty strings from padding.
+        let none = std::iter::empty;
+        assert_eq!(b"ab\0\0".to_vec(), names_data(b"ab\0\0", 2, none()));
+        assert_eq!(b"ab\0".to_vec(), names_data(b"ab\0\0", 1, none()));
+        assert_eq!(b"ab\0".to_vec(), names_data(b"ab\0\0", 0, none()));
+        assert_eq!(b"a\0bc\0".to_vec(), names_data(b"a\0bc\0\0\0\0", 7, none()));
+        assert_eq!(Vec::<u8>::new(), names_data(b"\0\0\0", 0, none()));
+        // Unterminated strings are kept as is.
+        assert_eq!(b"ab".to_vec(), names_data(b"ab", 1, none()));
+
+        // Referenced empty strings aren't padding.
+        assert_eq!(
+            b"ab\0\0".to_vec(),
+            names_data(b"ab\0\0", 0, [0, 3].into_iter())
+        );
+        assert_eq!(
+            b"ab\0".to_vec(),
+            names_data(b"ab\0\0", 0, [0, 9].into_iter())
+        );
+    }
+
+    #[test]
+    fn repair_child_count_overflow() {
+        let entry = |parent_index| Entry {
+            name_offset: 0,
+            attribute_count: 0,
+            child_count: 0,
+            attribute_start_index: -1,
+            unk1: -1,
+            parent_index,
+            unk2: -1,
+        };
+        let mut xmb = Xmb::from(&XmbFile {
+            entries: vec![XmbFileEntry::new("a")],
+        });
+        xmb.entries
+            .extend(std::iter::repeat_with(|| entry(0)).take(u16::MAX as usize + 1));
+
+        let repairs = xmb.repair();
+        assert!(repairs.contains(&Repair::ChildCountOverflow {
+            index: 0,
+            old: 0,
+            children: u16::MAX as usize + 1
+        }));
+        assert_eq!(u16::MAX, xmb.entries[0].child_count);
+    }
+
+    #[test]
+    fn repair_empty_xmb() {
+        let mut xmb = Xmb::from(&xmb! {});
+        assert!(xmb.repair().is_empty());
+
+        xmb.entry_count = 3;
+        assert_eq!(
+            vec![Repair::Count {
+                field: "entry_count",
+                old: 3,
+                new: 0
+            }],
+            xmb.repair()
+        );
+    }
+
+    #[test]
+    fn repair_invalid_parents() {
+        let mut xmb = Xmb::from(&xmb! {
+            a {
+                b;
+                c;
+                d;
+            }
+        });
+
+        // Cyclic parents count as children, and invalid parents are ignored.
+        xmb.entries[1].parent_index = 2;
+        xmb.entries[2].parent_index = 1;
+        xmb.entries[3].parent_index = 10;
+        let repairs = xmb.repair();
+        assert!(repairs.contains(&Repair::ChildCount {
+            index: 0,
+            old: 3,
+            new: 0
+        }));
+        assert_eq!(
+            vec![0, 1, 1, 0],
+            xmb.entries
+                .iter()
+                .map(|e| e.child_count)
+                .collect::<Vec<_>>()
+        );
+        assert!(xmb.verify_unk1().is_empty());
+        assert!(xmb.repair().is_empty());
+    }
+
+    #[test]
+    fn repair_duplicate_ids() {
+        let mut xmb = Xmb::from(&xmb! {
+            a(id = "x") {
+                b(id = "y");
+                c(id = "x");
+            }
+        });
+        let expected: Vec<_> = xmb
+            .mapped_entries
+            .iter()
+            .map(|e| (e.value_offset, e.entry_index))
+            .collect();
+
+        // The last entry with an id should be used like when building.
+        xmb.mapped_entries.reverse();
+        xmb.mapped_entries[0].entry_index = 0;
+        assert_eq!(vec![Repair::MappedEntries], xmb.repair());
+        assert_eq!(
+            expected,
+            xmb.mapped_entries
+                .iter()
+                .map(|e| (e.value_offset, e.entry_index))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(2, expected.len());
+    }
+
+    #[test]
+    fn repair_invalid_strings() {
+        let mut xmb = Xmb::from(&xmb! {
+            a(id = "x");
+        });
+
+        // Strings with invalid offsets aren't included in the lookup tables.
+        xmb.attributes[0].value_offset = 100;
+        xmb.string_offsets.push(100);
+        let repairs = xmb.repair();
+        assert!(repairs.contains(&Repair::StringOffsets));
+        assert!(repairs.contains(&Repair::MappedEntries));
+        assert_eq!(2, xmb.string_offsets.len());
+        assert!(xmb.mapped_entries.is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+//! Checks for values in an [Xmb] that differ from the values calculated when building.
+//!
+//! The [unk1](crate::xmb::Entry::unk1) values are recalculated from the parent indices,
+//! so a mismatch means rebuilding the file won't produce the same values.
+use crate::builder::calculate_unk1_values;
+use crate::events::{entry_children, read_string};
+use crate::xmb::Xmb;
+
+/// An entry with a stored [unk1](crate::xmb::Entry::unk1) value that differs from the calculated value.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Unk1Mismatch {
+    /// The index in [entries](crate::xmb::Xmb::entries).
+    pub index: usize,
+    /// The entry name or `None` if the name offset is invalid.
+    pub name: Option<String>,
+    /// The index of the root followed by the child index for each level
+    /// like [IndexedEntry::path](crate::index::IndexedEntry::path)
+    /// or `None` if the entry isn't reachable from a root entry.
+    pub path: Option<Vec<usize>>,
+    /// The value stored in the file.
+    pub actual: i16,
+    /// The value calculated from the parent indices.
+    pub expected: i16,
+}
+
+impl std::fmt::Display for Unk1Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "entry {} {:?} at {} has unk1 {} instead of {}",
+            self.index,
+            self.name.as_deref().unwrap_or_default(),
+            match &self.path {
+                Some(path) => format!("{path:?}"),
+                None => "an unreachable position".to_string(),
+            },
+            self.actual,
+            self.expected
+        )
+    }
+}
+
+impl Xmb {
+    /// Calculate [unk1](crate::xmb::Entry::unk1) for each entry from the parent indices
+    /// and find the entries with different values.
+    /// Returns an empty list if all values match.
+    pub fn verify_unk1(&self) -> Vec<Unk1Mismatch> {
+        let parent_indices: Vec<_> = self
+            .entries
+            .iter()
+            .map(|e| usize::try_from(e.parent_index).ok())
+            .collect();
+        let unk1_values = calculate_unk1_values(&parent_indices);
+
+        let mut mismatches: Vec<_> = self
+            .entries
+            .iter()
+            .zip(unk1_values)
+            .enumerate()
+            .filter(|(_, (e, unk1))| e.unk1 != *unk1 as i16)
+            .map(|(index, (e, unk1))| Unk1Mismatch {
+                index,
+                name: read_string(&self.string_names.data, e.name_offset).map(Into::into),
+                path: None,
+                actual: e.unk1,
+                expected: unk1 as i16,
+            })
+            .collect();
+
+        if !mismatches.is_empty() {
+            let paths = self.entry_paths();
+            for mismatch in &mut mismatches {
+                mismatch.path = paths[mismatch.index].clone();
+            }
+        }
+
+        mismatches
+    }
+
+    // The path of each entry or None for entries not reachable from the roots.
+    pub(crate) fn entry_paths(&self) -> Vec<Option<Vec<usize>>> {
+        let children = entry_children(self);
+        let mut paths = vec![None; self.entries.len()];
+
+        let mut stack: Vec<_> = children[self.entries.len()]
+            .iter()
+            .enumerate()
+            .map(|(i, root)| (*root, vec![i]))
+            .collect();
+        while let Some((index, path)) = stack.pop() {
+            // Each entry has a single parent, so entries in a cycle aren't reachable from a root.
+            for (i, child) in children[index].iter().enumerate() {
+                stack.push((*child, [path.as_slice(), &[i]].concat()));
+            }
+            paths[index] = Some(path);
+        }
+
+        paths
+    }
+}
@@ -0,0 +1,289 @@
+//! Traversals over the entries of an [XmbFile] with [Visitor] and [VisitorMut].
+//!
+//! Entries are visited in the same order as [entries](crate::xmb::Xmb::entries)
+//! when converted to [Xmb](crate::xmb::Xmb).
+//! All siblings are visited before any of their children,
+//! and an entry is finished after all of its descendants have been finished.
+//!
+//! Unlike the start and end tags in XML, the hooks aren't nested.
+//! An entry can be visited before the previous sibling is finished.
+//!
+//! ```text
+//! <a>         visit a
+//!   <b>       visit b, visit c
+//!     <d/>    visit d, finish d, finish b
+//!   </b>
+//!   <c/>      finish c, finish a
+//! </a>
+//! ```
+use crate::{XmbFile, XmbFileEntry};
+
+/// Hooks for visiting entries with [XmbFile::walk].
+///
+/// The `path` is the index into [entries](crate::XmbFile::entries)
+/// followed by the index into [children](crate::XmbFileEntry::children) for each level.
+pub trait Visitor {
+    /// Called for each entry before visiting its children.
+    fn visit(&mut self, _entry: &XmbFileEntry, _path: &[usize]) {}
+
+    /// Called for each entry after finishing all of its descendants.
+    fn finish(&mut self, _entry: &XmbFileEntry, _path: &[usize]) {}
+}
+
+/// Hooks for visiting and editing entries with [XmbFile::walk_mut].
+///
+/// Changes to the children in [visit](#method.visit) affect which children are visited.
+/// See [Visitor] for details on paths.
+pub trait VisitorMut {
+    /// Called for each entry before visiting its children.
+    fn visit(&mut self, _entry: &mut XmbFileEntry, _path: &[usize]) {}
+
+    /// Called for each entry after finishing all of its descendants.
+    fn finish(&mut self, _entry: &mut XmbFileEntry, _path: &[usize]) {}
+}
+
+/// Methods for visiting all entries.
+/// # Examples
+/**
+```rust
+use xmb_lib::{XmbFile, XmbFileEntry};
+use xmb_lib::visit::VisitorMut;
+
+struct RenameAttribute;
+
+impl VisitorMut for RenameAttribute {
+    fn visit(&mut self, entry: &mut XmbFileEntry, _path: &[usize]) {
+        if let Some(value) = entry.remove_attr("buffer") {
+            entry.set_attr("buffer_index", value);
+        }
+    }
+}
+
+let mut xmb_file = XmbFile {
+    entries: vec![XmbFileEntry::new("draw")
+        .child(XmbFileEntry::new("draw").attr("buffer", "0"))
+        .child(XmbFileEntry::new("shadow"))],
+};
+xmb_file.walk_mut(&mut RenameAttribute);
+xmb_file.retain(|e, _| e.name != "shadow");
+```
+ */
+impl XmbFile {
+    /// Visit all entries in the order described in the [module docs](crate::visit).
+    pub fn walk<V: Visitor + ?Sized>(&self, visitor: &mut V) {
+        walk_recursive(&self.entries, &mut Vec::new(), visitor);
+    }
+
+    /// Visit and edit all entries in the order described in the [module docs](crate::visit).
+    pub fn walk_mut<V: VisitorMut + ?Sized>(&mut self, visitor: &mut V) {
+        walk_mut_recursive(&mut self.entries, &mut Vec::new(), visitor);
+    }
+
+    /// Remove all entries and their descendants where `f` returns `false`.
+    ///
+    /// Entries are checked in the same order as [walk](#method.walk),
+    /// and the descendants of removed entries aren't checked.
+    /// Paths are the locations of entries before removing any entries.
+    pub fn retain<F: FnMut(&XmbFileEntry, &[usize]) -> bool>(&mut self, mut f: F) {
+        retain_recursive(&mut self.entries, &mut Vec::new(), &mut f);
+    }
+}
+
+fn walk_recursive<V: Visitor + ?Sized>(
+    children: &[XmbFileEntry],
+    path: &mut Vec<usize>,
+    visitor: &mut V,
+) {
+    for (i, child) in children.iter().enumerate() {
+        path.push(i);
+        visitor.visit(child, path);
+        path.pop();
+    }
+
+    for (i, child) in children.iter().enumerate() {
+        path.push(i);
+        walk_recursive(&child.children, path, visitor);
+        visitor.finish(child, path);
+        path.pop();
+    }
+}
+
+fn walk_mut_recursive<V: VisitorMut + ?Sized>(
+    children: &mut [XmbFileEntry],
+    path: &mut Vec<usize>,
+    visitor: &mut V,
+) {
+    for (i, child) in children.iter_mut().enumerate() {
+        path.push(i);
+        visitor.visit(child, path);
+        path.pop();
+    }
+
+    for (i, child) in children.iter_mut().enumerate() {
+        path.push(i);
+        walk_mut_recursive(&mut child.children, path, visitor);
+        visitor.finish(child, path);
+        path.pop();
+    }
+}
+
+fn retain_recursive<F: FnMut(&XmbFileEntry, &[usize]) -> bool>(
+    children: &mut Vec<XmbFileEntry>,
+    path: &mut Vec<usize>,
+    f: &mut F,
+) {
+    let keep: Vec<_> = children
+        .iter()
+        .enumerate()
+        .map(|(i, child)| {
+            path.push(i);
+            let keep = f(child, path);
+            path.pop();
+            keep
+        })
+        .collect();
+
+    for (i, child) in children.iter_mut().enumerate() {
+        if keep[i] {
+            path.push(i);
+            retain_recursive(&mut child.children, path, f);
+            path.pop();
+        }
+    }
+
+    let mut keep = keep.into_iter();
+    children.retain(|_| keep.next().unwrap_or_default());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::read_string_bytes;
+    use crate::xmb::Xmb;
+    use indexmap::IndexMap;
+
+    struct Names(Vec<String>);
+
+    impl Visitor for Names {
+        fn visit(&mut self, entry: &XmbFileEntry, path: &[usize]) {
+            self.0.push(format!("visit {} {path:?}", entry.name));
+        }
+
+        fn finish(&mut self, entry: &XmbFileEntry, path: &[usize]) {
+            self.0.push(format!("finish {} {path:?}", entry.name));
+        }
+    }
+
+    fn xmb_file() -> XmbFile {
+        XmbFile {
+            entries: vec![XmbFileEntry::new("a")
+                .child(XmbFileEntry::new("b").child(XmbFileEntry::new("d")))
+                .child(XmbFileEntry::new("c").attr("id", "c"))],
+        }
+    }
+
+    #[test]
+    fn walk() {
+        let mut names = Names(Vec::new());
+        xmb_file().walk(&mut names);
+        assert_eq!(
+            vec![
+                "visit a [0]",
+                "visit b [0, 0]",
+                "visit c [0, 1]",
+                "visit d [0, 0, 0]",
+                "finish d [0, 0, 0]",
+                "finish b [0, 0]",
+                "finish c [0, 1]",
+                "finish a [0]"
+            ],
+            names.0
+        );
+    }
+
+    #[test]
+    fn walk_empty() {
+        let mut names = Names(Vec::new());
+        XmbFile {
+            entries: Vec::new(),
+        }
+        .walk(&mut names);
+        assert!(names.0.is_empty());
+    }
+
+    #[test]
+    fn walk_mut_flattened_order() {
+        // The visit order should match the flattened order of the entries.
+        struct VisitNames(Vec<String>);
+
+        impl VisitorMut for VisitNames {
+            fn visit(&mut self, entry: &mut XmbFileEntry, _path: &[usize]) {
+                self.0.push(entry.name.clone());
+                entry.set_attr("order", self.0.len().to_string());
+            }
+        }
+
+        let mut xmb_file = xmb_file();
+        let mut names = VisitNames(Vec::new());
+        xmb_file.walk_mut(&mut names);
+
+        let xmb = Xmb::from(&xmb_file);
+        let entry_names: Vec<_> = xmb
+            .entries
+            .iter()
+            .map(|e| {
+                let bytes = read_string_bytes(&xmb.string_names.data, e.name_offset);
+                String::from_utf8(bytes.unwrap().to_vec()).unwrap()
+            })
+            .collect();
+        assert_eq!(entry_names, names.0);
+        assert_eq!(Some("3"), xmb_file.entries[0].children[1].get_attr("order"));
+    }
+
+    #[test]
+    fn walk_mut_add_children() {
+        // Children added when visiting an entry should also be visited.
+        struct AddChild;
+
+        impl VisitorMut for AddChild {
+            fn visit(&mut self, entry: &mut XmbFileEntry, path: &[usize]) {
+                if path.len() < 3 {
+                    entry.children.push(XmbFileEntry::new("new"));
+                }
+            }
+        }
+
+        let mut xmb_file = XmbFile {
+            entries: vec![XmbFileEntry::new("a")],
+        };
+        xmb_file.walk_mut(&mut AddChild);
+        assert_eq!(
+            XmbFile {
+                entries: vec![XmbFileEntry::new("a")
+                    .child(XmbFileEntry::new("new").child(XmbFileEntry::new("new")))],
+            },
+            xmb_file
+        );
+    }
+
+    #[test]
+    fn retain() {
+        let mut xmb_file = xmb_file();
+        let mut paths = Vec::new();
+        xmb_file.retain(|e, path| {
+            paths.push(path.to_vec());
+            e.name != "b"
+        });
+        assert_eq!(vec![vec![0], vec![0, 0], vec![0, 1]], paths);
+        assert_eq!(
+            XmbFileEntry::new("a").child(XmbFileEntry::new("c").attr("id", "c")),
+            XmbFileEntry {
+                attributes: IndexMap::new(),
+                ..xmb_file.entries[0].clone()
+            }
+        );
+
+        xmb_file.retain(|_, _| false);
+        assert!(xmb_file.entries.is_empty());
+    }
+}
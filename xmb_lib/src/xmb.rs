@@ -24,7 +24,8 @@ pub struct Xmb {
     pub string_count: u32,
     pub mapped_entry_count: u32,
 
-    /// Offsets for the values in [string_values](struct.Xmb.html#structfield.string_values) sorted alphabetically.
+    /// Offsets for the strings in [string_names](struct.Xmb.html#structfield.string_names) sorted by string.
+    /// See [Collation](crate::collation::Collation) for possible orderings.
     #[br(parse_with = FilePtr32::parse)]
     #[br(args { inner: args! { count: string_count as usize } })]
     #[xc3(offset(u32))]
@@ -42,7 +43,8 @@ pub struct Xmb {
     #[xc3(offset(u32))]
     pub attributes: Vec<Attribute>,
 
-    /// A lookup table for the `"id"` attribute sorted alphabetically by value.
+    /// A lookup table for the `"id"` attribute sorted by value.
+    /// See [Collation](crate::collation::Collation) for possible orderings.
     #[br(parse_with = FilePtr32::parse)]
     #[br(args { inner: args! { count: mapped_entry_count as usize } })]
     #[xc3(offset(u32))]
@@ -168,7 +170,7 @@ impl Xmb {
 
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
         // Buffer the entire file for performance since most XMB files are small.
-        let mut reader = std::io::Cursor::new(std::fs::read(path).unwrap());
+        let mut reader = std::io::Cursor::new(std::fs::read(path)?);
         let xmb: Xmb = reader.read_le()?;
         Ok(xmb)
     }
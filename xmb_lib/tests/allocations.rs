@@ -0,0 +1,63 @@
+// Count allocations with a global allocator, so this needs its own test binary.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use xmb_lib::xmb::Xmb;
+use xmb_lib::{XmbFile, XmbFileEntry};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let start = ALLOCATIONS.load(Ordering::Relaxed);
+    let result = f();
+    (result, ALLOCATIONS.load(Ordering::Relaxed) - start)
+}
+
+// Allocations for the test file measured with XmbFile::try_from in the baseline version of the library.
+// The baseline read each string with read_name and read_value and collected the children of each entry.
+const BASELINE_ALLOCATIONS: usize = 2815;
+
+#[test]
+fn xmb_file_from_xmb_allocations() {
+    let root = XmbFileEntry::new("model").children((0..100).map(|i| {
+        XmbFileEntry::new("draw")
+            .attr("id", format!("draw{i}"))
+            .attr("type", "effect_main")
+            .child(XmbFileEntry::new("material").attr("shader", "SFX_PBS_0100000008008269"))
+    }));
+    let expected = XmbFile {
+        entries: vec![root],
+    };
+    let xmb = Xmb::from(&expected);
+
+    let (xmb_file, allocations) = count_allocations(|| XmbFile::try_from(&xmb).unwrap());
+    assert_eq!(expected, xmb_file);
+
+    // Each name and value should only need a single allocation.
+    let strings = 1 + 100 * ((1 + 2 * 2) + (1 + 2));
+    assert!(
+        allocations * 3 < BASELINE_ALLOCATIONS * 2,
+        "{allocations} allocations with {BASELINE_ALLOCATIONS} for the baseline"
+    );
+    assert!(allocations < strings * 2, "{allocations} allocations");
+}
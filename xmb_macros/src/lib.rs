@@ -0,0 +1,62 @@
+use proc_macro::TokenStream;
+use proc_macro2::Literal;
+use quote::quote;
+use std::path::PathBuf;
+use syn::{parse_macro_input, LitStr};
+use xmb_lib::{parse_xml, xmb::Xmb, FromXmlOptions, XmbFile};
+
+/// Convert an XML file to XMB at compile time and include the bytes as a `&'static [u8]`.
+///
+/// The path is relative to the directory containing the crate's `Cargo.toml`.
+/// The XML is converted using [XmbFile::from_xml_with_options] in strict mode,
+/// so text or CDATA that can't be stored in XMB fails the build.
+/// # Examples
+/**
+```rust ignore
+use xmb_macros::include_xmb;
+
+static MODEL: &[u8] = include_xmb!("data/model.xml");
+```
+ */
+#[proc_macro]
+pub fn include_xmb(input: TokenStream) -> TokenStream {
+    let path = parse_macro_input!(input as LitStr);
+    include_xmb_impl(&path)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn include_xmb_impl(path: &LitStr) -> syn::Result<proc_macro2::TokenStream> {
+    let error = |message: String| syn::Error::new(path.span(), message);
+
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")
+        .map_err(|_| error("CARGO_MANIFEST_DIR is not set".to_string()))?;
+    let full_path = PathBuf::from(manifest_dir).join(path.value());
+
+    let xml_text = std::fs::read(&full_path)
+        .map_err(|e| error(format!("failed to read {}: {e}", full_path.display())))?;
+    let element = parse_xml(xml_text.as_slice())
+        .map_err(|e| error(format!("failed to parse {}: {e}", full_path.display())))?;
+
+    let options = FromXmlOptions { strict: true };
+    let (xmb_file, _) = XmbFile::from_xml_with_options(&element, &options)
+        .map_err(|e| error(format!("failed to convert {}: {e}", full_path.display())))?;
+
+    let mut writer = std::io::Cursor::new(Vec::new());
+    Xmb::from(&xmb_file)
+        .write(&mut writer)
+        .map_err(|e| error(format!("failed to write XMB: {e}")))?;
+    let bytes = Literal::byte_string(writer.get_ref());
+
+    // Including the XML tracks the file so changes trigger a rebuild.
+    let full_path = full_path
+        .to_str()
+        .ok_or_else(|| error(format!("path {} is not valid UTF-8", full_path.display())))?;
+
+    Ok(quote! {
+        {
+            const _: &[u8] = include_bytes!(#full_path);
+            #bytes as &'static [u8]
+        }
+    })
+}
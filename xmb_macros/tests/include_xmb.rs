@@ -0,0 +1,25 @@
+use xmb_lib::{xmb, XmbFile};
+use xmb_macros::include_xmb;
+
+static MODEL: &[u8] = include_xmb!("tests/model.xml");
+
+#[test]
+fn include_xmb_model() {
+    let expected = xmb! {
+        model(type = "effect_main") {
+            shadow(caster = "0");
+            draw {
+                draw(buffer = "0", type = "main");
+                draw(action = "1", type = "normalmap");
+            }
+        }
+    };
+
+    let xmb_file = XmbFile::read(&mut std::io::Cursor::new(MODEL)).unwrap();
+    assert_eq!(expected, xmb_file);
+
+    // The bytes should match converting at runtime.
+    let mut writer = std::io::Cursor::new(Vec::new());
+    expected.write(&mut writer).unwrap();
+    assert_eq!(writer.into_inner(), MODEL);
+}
@@ -0,0 +1,397 @@
+//! Python bindings for [xmb_lib] using [pyo3].
+//!
+//! Entries are stored as Python objects with a [dict](PyDict) for attributes
+//! and a [list](PyList) for children, so they can be edited like any other Python data.
+//! Conversions to and from the Rust types only happen when reading or writing.
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyRecursionError, PyUserWarning};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use xmb_lib::{FromXmlOptions, XmlOptions};
+
+// Python lists can contain themselves, so limit the depth to avoid overflowing the stack.
+// Reading uses the same limit, so any file that can be read can also be written.
+const MAX_DEPTH: usize = 256;
+
+create_exception!(
+    xmb_py,
+    XmbError,
+    PyException,
+    "The base class for errors from xmb_py."
+);
+create_exception!(
+    xmb_py,
+    ReadXmbError,
+    XmbError,
+    "Reading an XMB file failed."
+);
+create_exception!(
+    xmb_py,
+    WriteXmbError,
+    XmbError,
+    "Writing an XMB file failed."
+);
+create_exception!(
+    xmb_py,
+    ReadXmlError,
+    XmbError,
+    "Converting XML text to entries failed."
+);
+create_exception!(
+    xmb_py,
+    WriteXmlError,
+    XmbError,
+    "Converting entries to XML text failed."
+);
+
+/// A tree of named entries with named attributes corresponding to an XML document.
+#[pyclass(get_all, set_all)]
+pub struct XmbFile {
+    /// The root entries. Files converted from XML have a single root entry.
+    pub entries: Py<PyList>,
+}
+
+/// A named entry with attributes and child entries corresponding to an XML element.
+#[pyclass(get_all, set_all)]
+pub struct XmbFileEntry {
+    pub name: String,
+    /// The attribute names and values in order as a `dict[str, str]`.
+    pub attributes: Py<PyDict>,
+    /// The child entries as a `list[XmbFileEntry]`.
+    pub children: Py<PyList>,
+}
+
+#[pymethods]
+impl XmbFile {
+    #[new]
+    #[pyo3(signature = (entries=None))]
+    fn new(py: Python, entries: Option<Py<PyList>>) -> Self {
+        Self {
+            entries: entries.unwrap_or_else(|| PyList::empty(py).unbind()),
+        }
+    }
+
+    /// Write the XMB data to the file at `path`.
+    fn write(&self, py: Python, path: &str) -> PyResult<()> {
+        xmb_file_rs(py, self)?
+            .write_to_file(path)
+            .map_err(|e| WriteXmbError::new_err(e.to_string()))
+    }
+
+    /// Convert to XML text with the same formatting as the `xmb` command line program.
+    fn to_xml(&self, py: Python) -> PyResult<String> {
+        xmb_file_rs(py, self)?
+            .to_xml_string(&XmlOptions::ssbu_tools())
+            .map_err(|e| WriteXmlError::new_err(e.to_string()))
+    }
+}
+
+#[pymethods]
+impl XmbFileEntry {
+    #[new]
+    #[pyo3(signature = (name, attributes=None, children=None))]
+    fn new(
+        py: Python,
+        name: String,
+        attributes: Option<Py<PyDict>>,
+        children: Option<Py<PyList>>,
+    ) -> Self {
+        Self {
+            name,
+            attributes: attributes.unwrap_or_else(|| PyDict::new(py).unbind()),
+            children: children.unwrap_or_else(|| PyList::empty(py).unbind()),
+        }
+    }
+}
+
+/// Read the XMB file at `path`.
+#[pyfunction]
+fn read(py: Python, path: &str) -> PyResult<XmbFile> {
+    let xmb_file =
+        xmb_lib::XmbFile::from_file(path).map_err(|e| ReadXmbError::new_err(e.to_string()))?;
+    xmb_file_py(py, &xmb_file)
+}
+
+/// Convert the XML document in `text` to entries.
+/// Text and CDATA can't be stored in XMB and are ignored with a warning.
+#[pyfunction]
+fn from_xml(py: Python, text: &str) -> PyResult<XmbFile> {
+    let (xmb_file, dropped) = xmb_lib::XmbFile::from_xml_str(text, &FromXmlOptions::default())
+        .map_err(|e| ReadXmlError::new_err(e.to_string()))?;
+
+    let category = py.get_type::<PyUserWarning>();
+    for d in dropped {
+        let message = std::ffi::CString::new(format!("Ignoring {d}")).unwrap_or_default();
+        PyErr::warn(py, &category, &message, 0)?;
+    }
+
+    xmb_file_py(py, &xmb_file)
+}
+
+#[pymodule]
+fn xmb_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<XmbFile>()?;
+    m.add_class::<XmbFileEntry>()?;
+    m.add_function(wrap_pyfunction!(read, m)?)?;
+    m.add_function(wrap_pyfunction!(from_xml, m)?)?;
+    m.add("XmbError", m.py().get_type::<XmbError>())?;
+    m.add("ReadXmbError", m.py().get_type::<ReadXmbError>())?;
+    m.add("WriteXmbError", m.py().get_type::<WriteXmbError>())?;
+    m.add("ReadXmlError", m.py().get_type::<ReadXmlError>())?;
+    m.add("WriteXmlError", m.py().get_type::<WriteXmlError>())?;
+    Ok(())
+}
+
+fn xmb_file_py(py: Python, xmb_file: &xmb_lib::XmbFile) -> PyResult<XmbFile> {
+    Ok(XmbFile {
+        entries: entries_py(py, &xmb_file.entries, 0)?,
+    })
+}
+
+fn check_depth(depth: usize) -> PyResult<()> {
+    if depth > MAX_DEPTH {
+        Err(PyRecursionError::new_err(format!(
+            "entries are nested more than {MAX_DEPTH} levels deep"
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+fn entries_py(py: Python, entries: &[xmb_lib::XmbFileEntry], depth: usize) -> PyResult<Py<PyList>> {
+    check_depth(depth)?;
+    let entries = entries
+        .iter()
+        .map(|e| Py::new(py, entry_py(py, e, depth)?))
+        .collect::<PyResult<Vec<_>>>()?;
+    Ok(PyList::new(py, entries)?.unbind())
+}
+
+fn entry_py(py: Python, entry: &xmb_lib::XmbFileEntry, depth: usize) -> PyResult<XmbFileEntry> {
+    let attributes = PyDict::new(py);
+    for (k, v) in &entry.attributes {
+        attributes.set_item(k, v)?;
+    }
+
+    Ok(XmbFileEntry {
+        name: entry.name.clone(),
+        attributes: attributes.unbind(),
+        children: entries_py(py, &entry.children, depth + 1)?,
+    })
+}
+
+fn xmb_file_rs(py: Python, xmb_file: &XmbFile) -> PyResult<xmb_lib::XmbFile> {
+    Ok(xmb_lib::XmbFile {
+        entries: entries_rs(py, &xmb_file.entries, 0)?,
+    })
+}
+
+fn entries_rs(
+    py: Python,
+    entries: &Py<PyList>,
+    depth: usize,
+) -> PyResult<Vec<xmb_lib::XmbFileEntry>> {
+    check_depth(depth)?;
+    entries
+        .bind(py)
+        .iter()
+        .map(|e| {
+            let entry: PyRef<XmbFileEntry> = e.extract()?;
+            entry_rs(py, &entry, depth)
+        })
+        .collect()
+}
+
+fn entry_rs(py: Python, entry: &XmbFileEntry, depth: usize) -> PyResult<xmb_lib::XmbFileEntry> {
+    let attributes = entry
+        .attributes
+        .bind(py)
+        .iter()
+        .map(|(k, v)| Ok((k.extract()?, v.extract()?)))
+        .collect::<PyResult<_>>()?;
+
+    Ok(xmb_lib::XmbFileEntry {
+        name: entry.name.clone(),
+        attributes,
+        children: entries_rs(py, &entry.children, depth + 1)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::exceptions::PyTypeError;
+
+    fn entry(py: Python, name: &str, children: Vec<Py<XmbFileEntry>>) -> Py<XmbFileEntry> {
+        let children = PyList::new(py, children).unwrap().unbind();
+        Py::new(
+            py,
+            XmbFileEntry::new(py, name.to_string(), None, Some(children)),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn from_xml_to_xml() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let text = "<model id=\"a\">text<draw buffer=\"0\"/></model>";
+            let xmb_file = from_xml(py, text).unwrap();
+
+            let entries = xmb_file.entries.bind(py);
+            assert_eq!(1, entries.len());
+            let model: PyRef<XmbFileEntry> = entries.get_item(0).unwrap().extract().unwrap();
+            assert_eq!("model", model.name);
+            assert_eq!(
+                "a",
+                model
+                    .attributes
+                    .bind(py)
+                    .get_item("id")
+                    .unwrap()
+                    .unwrap()
+                    .extract::<String>()
+                    .unwrap()
+            );
+            assert_eq!(1, model.children.bind(py).len());
+
+            let expected = xmb_lib::XmbFile {
+                entries: vec![xmb_lib::XmbFileEntry::new("model")
+                    .attr("id", "a")
+                    .child(xmb_lib::XmbFileEntry::new("draw").attr("buffer", "0"))],
+            };
+            assert_eq!(expected, xmb_file_rs(py, &xmb_file).unwrap());
+            assert_eq!(
+                expected.to_xml_string(&XmlOptions::ssbu_tools()).unwrap(),
+                xmb_file.to_xml(py).unwrap()
+            );
+        });
+    }
+
+    #[test]
+    fn edit_entries() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let root = entry(py, "root", Vec::new());
+            root.borrow(py)
+                .attributes
+                .bind(py)
+                .set_item("x", "1")
+                .unwrap();
+            root.borrow(py)
+                .children
+                .bind(py)
+                .append(entry(py, "child", Vec::new()))
+                .unwrap();
+            let xmb_file = XmbFile::new(py, Some(PyList::new(py, [root]).unwrap().unbind()));
+
+            assert_eq!(
+                xmb_lib::XmbFile {
+                    entries: vec![xmb_lib::XmbFileEntry::new("root")
+                        .attr("x", "1")
+                        .child(xmb_lib::XmbFileEntry::new("child"))],
+                },
+                xmb_file_rs(py, &xmb_file).unwrap()
+            );
+        });
+    }
+
+    #[test]
+    fn invalid_entries() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let entries = PyList::new(py, ["root"]).unwrap().unbind();
+            let err = xmb_file_rs(py, &XmbFile::new(py, Some(entries))).unwrap_err();
+            assert!(err.is_instance_of::<PyTypeError>(py));
+
+            let root = entry(py, "root", Vec::new());
+            root.borrow(py)
+                .attributes
+                .bind(py)
+                .set_item("x", 1)
+                .unwrap();
+            let entries = PyList::new(py, [root]).unwrap().unbind();
+            let err = xmb_file_rs(py, &XmbFile::new(py, Some(entries))).unwrap_err();
+            assert!(err.is_instance_of::<PyTypeError>(py));
+        });
+    }
+
+    #[test]
+    fn error_types() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let err = from_xml(py, "<root>").err().unwrap();
+            assert!(err.is_instance_of::<ReadXmlError>(py));
+            assert!(err.is_instance_of::<XmbError>(py));
+
+            let err = read(py, "does_not_exist.xmb").err().unwrap();
+            assert!(err.is_instance_of::<ReadXmbError>(py));
+
+            // XML requires a single root element.
+            let xmb_file = XmbFile::new(py, None);
+            let err = xmb_file.to_xml(py).unwrap_err();
+            assert!(err.is_instance_of::<WriteXmlError>(py));
+
+            let err = xmb_file.write(py, "does_not_exist/file.xmb").unwrap_err();
+            assert!(err.is_instance_of::<WriteXmbError>(py));
+            assert!(!err.is_instance_of::<ReadXmbError>(py));
+        });
+    }
+
+    #[test]
+    fn self_referencing_entries() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let root = entry(py, "root", Vec::new());
+            let children = root.borrow(py).children.clone_ref(py);
+            children.bind(py).append(root.clone_ref(py)).unwrap();
+
+            let xmb_file = XmbFile::new(py, Some(PyList::new(py, [root]).unwrap().unbind()));
+            let err = xmb_file_rs(py, &xmb_file).unwrap_err();
+            assert!(err.is_instance_of::<PyRecursionError>(py));
+        });
+    }
+
+    #[test]
+    fn max_depth() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let nested = |depth: usize| {
+                let mut root = entry(py, "entry", Vec::new());
+                for _ in 1..depth {
+                    root = entry(py, "entry", vec![root]);
+                }
+                XmbFile::new(py, Some(PyList::new(py, [root]).unwrap().unbind()))
+            };
+            assert!(xmb_file_rs(py, &nested(MAX_DEPTH)).is_ok());
+            assert!(xmb_file_rs(py, &nested(MAX_DEPTH + 1))
+                .unwrap_err()
+                .is_instance_of::<PyRecursionError>(py));
+        });
+    }
+
+    #[test]
+    fn read_max_depth() {
+        // Files that can be read should also be writable.
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let nested = |depth: usize| {
+                let mut root = xmb_lib::XmbFileEntry::new("entry");
+                for _ in 1..depth {
+                    root = xmb_lib::XmbFileEntry::new("entry").child(root);
+                }
+                xmb_lib::XmbFile {
+                    entries: vec![root],
+                }
+            };
+            let xmb_file = nested(MAX_DEPTH);
+            let py_file = xmb_file_py(py, &xmb_file).unwrap();
+            assert_eq!(xmb_file, xmb_file_rs(py, &py_file).unwrap());
+
+            assert!(xmb_file_py(py, &nested(MAX_DEPTH + 1))
+                .err()
+                .unwrap()
+                .is_instance_of::<PyRecursionError>(py));
+        });
+    }
+}